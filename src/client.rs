@@ -1,103 +1,537 @@
+use futures::stream::{self, Stream, StreamExt};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::timeout;
+use socket2::{SockRef, TcpKeepalive};
 use std::error::Error;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::frame::{parse_stomp_frame, decompress_gzipped_data};
+use crate::error::PushPortError;
+use crate::frame::{compress_gzipped_data, parse_stomp_frame, FrameBuilder, StompFrame};
+use crate::heartbeat::{run_writer, Heartbeat, Outbox};
+use crate::message::Message;
+use crate::models::Pport;
+use crate::options::{AckMode, ConnectOptions, SendOptions, SubscribeOptions};
+use crate::reader::{run_reader, Connection};
+use crate::receipt::Receipts;
+use crate::subscription::{Acknowledged, MessageReceiver, Routes, Subscription};
+#[cfg(feature = "tls")]
+use crate::tls::connect_tls;
+use crate::transaction::Transaction;
+use crate::transport::Transport;
 
 /// A client for connecting to National Rails push port system.
+///
+/// Frames are read and written by background tasks, which stop when the client is dropped.
 pub struct NationalRailPushPortClient {
-    stream: TcpStream,
-    accumulated: Vec<u8>,
+    outbox: Outbox,
+    routes: Routes,
+    acknowledged: Acknowledged,
+    receipts: Receipts,
+    inbox: MessageReceiver,
+    inbox_read: Arc<AtomicBool>,
+    connection_info: ConnectionInfo,
+    receipt_timeout: Duration,
+    message_capacity: usize,
+    next_subscription_id: u64,
+    next_receipt_id: u64,
+    next_transaction_id: u64,
+}
+
+/// The values the server returned in its CONNECTED frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    /// The STOMP protocol version the server chose.
+    pub version: String,
+    /// The name and version of the server software, if given.
+    pub server: Option<String>,
+    /// The session identifier assigned by the server, if given.
+    pub session: Option<String>,
+    /// The server's heart-beat settings, in milliseconds: (guaranteed send interval, desired receive interval).
+    pub heart_beat: (u64, u64),
+}
+
+impl ConnectionInfo {
+    fn from_frame(frame: &StompFrame) -> Self {
+        // STOMP 1.0 servers do not send a version header.
+        let version = frame.header("version").unwrap_or("1.0").to_string();
+        let heart_beat = frame
+            .header("heart-beat")
+            .and_then(|value| value.split_once(','))
+            .and_then(|(cx, cy)| Some((cx.trim().parse().ok()?, cy.trim().parse().ok()?)))
+            .unwrap_or((0, 0));
+        Self {
+            version,
+            server: frame.header("server").map(str::to_string),
+            session: frame.header("session").map(str::to_string),
+            heart_beat,
+        }
+    }
+}
+
+/// Applies the socket options from `options` to a new TCP connection.
+fn configure_socket(stream: &TcpStream, options: &ConnectOptions) -> Result<(), PushPortError> {
+    let socket = SockRef::from(stream);
+    if let Some(idle) = options.tcp_keepalive {
+        socket.set_tcp_keepalive(&TcpKeepalive::new().with_time(idle))?;
+    }
+    if let Some((receive, send)) = options.socket_buffer_sizes {
+        socket.set_recv_buffer_size(receive)?;
+        socket.set_send_buffer_size(send)?;
+    }
+    Ok(())
+}
+
+/// Uses destinations starting with `/` as given, and treats anything else as a topic name.
+pub(crate) fn destination_path(destination: &str) -> String {
+    if destination.starts_with('/') {
+        destination.to_string()
+    } else {
+        format!("/topic/{}", destination)
+    }
+}
+
+/// Converts the server's reply to a CONNECT frame into an error, if it was not CONNECTED.
+fn handshake_error(frame: &StompFrame) -> PushPortError {
+    if frame.command() != "ERROR" {
+        return PushPortError::FrameParse(format!(
+            "expected CONNECTED frame, got {}",
+            frame.command()
+        ));
+    }
+
+    let message = frame.header("message").map(str::to_string);
+    let body = String::from_utf8_lossy(&frame.body).to_string();
+    let reason = message.as_deref().unwrap_or(&body).to_lowercase();
+    if ["password", "credentials", "authenticat", "login", "security"]
+        .iter()
+        .any(|hint| reason.contains(hint))
+    {
+        return PushPortError::AuthenticationFailed {
+            message: message.unwrap_or(body),
+        };
+    }
+    PushPortError::HandshakeRejected {
+        message,
+        headers: frame.headers().to_vec(),
+        body,
+    }
 }
 
 impl NationalRailPushPortClient {
-    /// Connects to a STOMP server and performs the initial handshake.
+    /// Connects to a STOMP server and performs the initial handshake, using default options.
     pub async fn connect(
         host: &str,
         port: u16,
         username: &str,
         password: &str,
-    ) -> Result<Self, Box<dyn Error>> {
-        let address = format!("{}:{}", host, port);
-        let mut stream = TcpStream::connect(address).await?;
-        println!("Connected to STOMP server.");
+    ) -> Result<Self, PushPortError> {
+        Self::connect_with(ConnectOptions::new(host, port, username, password)).await
+    }
 
-        // Send the CONNECT frame.
-        let connect_frame = format!(
-            "CONNECT\naccept-version:1.2\nhost:{}\nlogin:{}\npasscode:{}\n\n\0",
-            host, username, password
+    /// Connects to a STOMP server using the given options and performs the initial handshake.
+    ///
+    /// Once connected, background tasks send any frames the client queues, keep the connection alive
+    /// with heart-beats, and route incoming messages to their subscriptions.
+    pub async fn connect_with(options: ConnectOptions) -> Result<Self, PushPortError> {
+        match options.connect_timeout {
+            Some(limit) => timeout(limit, Self::open(options))
+                .await
+                .map_err(|_| PushPortError::ConnectTimeout(limit))?,
+            None => Self::open(options).await,
+        }
+    }
+
+    /// Opens the TCP connection and any TLS session, then performs the handshake.
+    async fn open(options: ConnectOptions) -> Result<Self, PushPortError> {
+        let address = format!("{}:{}", options.host, options.port);
+        let stream = TcpStream::connect(address).await?;
+        configure_socket(&stream, &options)?;
+
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &options.tls {
+            let stream = connect_tls(stream, &options.host, tls).await?;
+            return Self::handshake(stream, options).await;
+        }
+        Self::handshake(stream, options).await
+    }
+
+    /// Performs the STOMP handshake over a connection that is already open, such as a proxy
+    /// tunnel, a Unix socket or one end of a `tokio::io::duplex` pair in tests.
+    ///
+    /// The virtual host in `options`, or failing that the host, is sent in the CONNECT frame's
+    /// `host` header. The connect timeout applies to the handshake. Settings for opening the
+    /// connection, such as the port, socket options and TLS, are ignored.
+    pub async fn from_stream<S: Transport>(
+        stream: S,
+        options: ConnectOptions,
+    ) -> Result<Self, PushPortError> {
+        match options.connect_timeout {
+            Some(limit) => timeout(limit, Self::handshake(stream, options))
+                .await
+                .map_err(|_| PushPortError::ConnectTimeout(limit))?,
+            None => Self::handshake(stream, options).await,
+        }
+    }
+
+    /// Sends CONNECT, waits for CONNECTED and starts the background tasks.
+    async fn handshake<S: Transport>(
+        mut stream: S,
+        options: ConnectOptions,
+    ) -> Result<Self, PushPortError> {
+        let client_heart_beat = (
+            options.heart_beat.0.as_millis() as u64,
+            options.heart_beat.1.as_millis() as u64,
         );
-        stream.write_all(connect_frame.as_bytes()).await?;
-        println!("Sent CONNECT frame:\n{}", connect_frame);
-
-        // Read the server's CONNECTED response.
-        let mut buffer = vec![0u8; 8192];
-        let n = stream.read(&mut buffer).await?;
-        if n == 0 {
-            return Err("No response received. Connection may have been closed.".into());
+        let mut connect_frame = FrameBuilder::new("CONNECT")
+            .header("accept-version", &options.accept_version)
+            .header(
+                "host",
+                options.virtual_host.as_deref().unwrap_or(&options.host),
+            )
+            .header("login", &options.username)
+            .header("passcode", &options.password)
+            .header(
+                "heart-beat",
+                format!("{},{}", client_heart_beat.0, client_heart_beat.1),
+            );
+        if let Some(client_id) = &options.client_id {
+            connect_frame = connect_frame.header("client-id", client_id);
+        }
+        let connect_frame = connect_frame.headers(options.headers.clone()).build();
+        connect_frame.validate()?;
+
+        // Send the CONNECT frame.
+        stream.write_all(&connect_frame.encode()).await?;
+
+        // Read until the server's reply forms a complete frame.
+        let mut accumulated = Vec::new();
+        let frame = loop {
+            if let Some((frame_len, frame)) = parse_stomp_frame(&accumulated)? {
+                // Anything after the reply belongs to the next frame.
+                accumulated.drain(..frame_len);
+                break frame;
+            }
+            let mut buffer = vec![0u8; options.read_buffer_size];
+            let n = stream.read(&mut buffer).await?;
+            if n == 0 {
+                return Err(PushPortError::ConnectionClosed);
+            }
+            accumulated.extend_from_slice(&buffer[..n]);
+        };
+        if frame.command() != "CONNECTED" {
+            return Err(handshake_error(&frame));
         }
-        println!("Received response:\n{}", String::from_utf8_lossy(&buffer[..n]));
+        let connection_info = ConnectionInfo::from_frame(&frame);
+
+        let heartbeat = Heartbeat::negotiate(
+            client_heart_beat,
+            connection_info.heart_beat,
+            options.heartbeat_grace,
+        );
+        let (reader, writer) = tokio::io::split(stream);
+        let (outbox, outgoing) = mpsc::unbounded_channel();
+        let (writer_guard, shutdown) = oneshot::channel();
+        let (failed, writer_failed) = oneshot::channel();
+        tokio::spawn(run_writer(
+            writer,
+            outgoing,
+            heartbeat.send,
+            shutdown,
+            failed,
+        ));
+
+        let routes = Routes::default();
+        let acknowledged = Acknowledged::default();
+        let receipts = Receipts::default();
+        let (inbox_sender, inbox) = mpsc::channel(options.message_capacity);
+        let inbox_read = Arc::new(AtomicBool::new(false));
+        let connection = Connection {
+            routes: routes.clone(),
+            acknowledged: acknowledged.clone(),
+            receipts: receipts.clone(),
+            inbox: inbox_sender,
+            inbox_read: inbox_read.clone(),
+            outbox: outbox.clone(),
+        };
+        tokio::spawn(run_reader(
+            reader,
+            accumulated,
+            heartbeat.receive_timeout,
+            options.read_buffer_size,
+            connection,
+            writer_guard,
+            writer_failed,
+        ));
 
         Ok(Self {
-            stream,
-            accumulated: Vec::new(),
+            outbox,
+            routes,
+            acknowledged,
+            receipts,
+            inbox,
+            inbox_read,
+            connection_info,
+            receipt_timeout: options.receipt_timeout,
+            message_capacity: options.message_capacity,
+            next_subscription_id: 1,
+            next_receipt_id: 1,
+            next_transaction_id: 1,
         })
     }
 
-    /// Sends a frame to the server.
-    pub async fn send_frame(&mut self, frame: &str) -> Result<(), Box<dyn Error>> {
-        self.stream.write_all(frame.as_bytes()).await?;
-        Ok(())
+    /// Returns the values the server negotiated in its CONNECTED frame.
+    pub fn connection_info(&self) -> &ConnectionInfo {
+        &self.connection_info
     }
 
-    /// Subscribes to a given topic.
-    pub async fn subscribe(&mut self, live_feed_topic: &str) -> Result<(), Box<dyn Error>> {
-        let subscribe_frame = format!(
-            "SUBSCRIBE\nid:sub-1\ndestination:/topic/{}\nack:auto\n\n\0",
-            live_feed_topic
-        );
-        self.send_frame(&subscribe_frame).await?;
-        println!("Sent SUBSCRIBE frame:\n{}", subscribe_frame);
-        Ok(())
+    /// Returns whether the connection is still open.
+    ///
+    /// Once this returns `false`, every request that needs the server fails with
+    /// [`PushPortError::ConnectionClosed`].
+    pub fn is_connected(&self) -> bool {
+        !self.outbox.is_closed()
+    }
+
+    /// Queues a frame to be sent to the server by the background writer task.
+    ///
+    /// Frames are built with [`FrameBuilder`], which takes care of escaping headers and setting
+    /// `content-length`.
+    pub async fn send_frame(&mut self, frame: &StompFrame) -> Result<(), PushPortError> {
+        self.outbox
+            .send(frame.encode())
+            .map_err(|_| PushPortError::ConnectionClosed)
+    }
+
+    /// Subscribes to a destination with automatic acknowledgement and returns a handle yielding its
+    /// messages.
+    ///
+    /// Destinations starting with `/`, such as `/queue/...` or `/topic/VirtualTopic...`, are used as
+    /// given; anything else is treated as a topic name and prefixed with `/topic/`. Each subscription
+    /// gets its own id, so several can share one connection.
+    pub async fn subscribe(&mut self, destination: &str) -> Result<Subscription, PushPortError> {
+        self.subscribe_with(destination, SubscribeOptions::default())
+            .await
+    }
+
+    /// Subscribes to a destination using the given options, such as the acknowledgement mode.
+    ///
+    /// See [`subscribe`](Self::subscribe) for how destinations are interpreted.
+    pub async fn subscribe_with(
+        &mut self,
+        destination: &str,
+        options: SubscribeOptions,
+    ) -> Result<Subscription, PushPortError> {
+        let destination = destination_path(destination);
+        let id = format!("sub-{}", self.next_subscription_id);
+        self.next_subscription_id += 1;
+
+        // Register the route first, so no message for the subscription can be missed.
+        let (sender, receiver) = mpsc::channel(self.message_capacity);
+        self.routes.lock().unwrap().insert(id.clone(), Some(sender));
+        if options.ack != AckMode::Auto {
+            self.acknowledged.lock().unwrap().insert(id.clone());
+        }
+
+        let mut subscribe_frame = FrameBuilder::new("SUBSCRIBE")
+            .header("id", &id)
+            .header("destination", &destination)
+            .header("ack", options.ack.as_str());
+        if let Some(name) = &options.durable_name {
+            subscribe_frame = subscribe_frame.header("activemq.subscriptionName", name);
+        }
+        let subscribe_frame = subscribe_frame.headers(options.headers).build();
+        if let Err(e) = self.send_frame(&subscribe_frame).await {
+            self.routes.lock().unwrap().remove(&id);
+            self.acknowledged.lock().unwrap().remove(&id);
+            return Err(PushPortError::SubscriptionFailed {
+                destination,
+                reason: e.to_string(),
+            });
+        }
+        Ok(Subscription::new(
+            id,
+            destination,
+            receiver,
+            self.outbox.clone(),
+            self.routes.clone(),
+        ))
+    }
+
+    /// Sends a message to a destination, with extra headers such as `persistent:true`.
+    ///
+    /// Destinations are interpreted as in [`subscribe`](Self::subscribe). The body is sent as given,
+    /// with a `content-length` header, which ActiveMQ delivers as a bytes message unless the
+    /// `amq-msg-type:text` header is also set. Use [`send_with`](Self::send_with) to compress the
+    /// body or wait for a receipt.
+    pub async fn send(
+        &mut self,
+        destination: &str,
+        headers: &[(&str, &str)],
+        body: impl Into<Vec<u8>>,
+    ) -> Result<(), PushPortError> {
+        let options = headers
+            .iter()
+            .fold(SendOptions::new(), |options, (name, value)| {
+                options.header(*name, *value)
+            });
+        self.send_with(destination, body, options).await
+    }
+
+    /// Sends a message to a destination using the given options.
+    ///
+    /// With [`SendOptions::receipt`], returns once the server has confirmed the message, or fails
+    /// with [`PushPortError::ReceiptTimeout`] if it does not in time.
+    pub async fn send_with(
+        &mut self,
+        destination: &str,
+        body: impl Into<Vec<u8>>,
+        options: SendOptions,
+    ) -> Result<(), PushPortError> {
+        let mut body = body.into();
+        if options.gzip {
+            body = compress_gzipped_data(&body)?;
+        }
+        let mut send_frame =
+            FrameBuilder::new("SEND").header("destination", destination_path(destination));
+
+        let receipt = if options.receipt {
+            let receipt_id = format!("send-{}", self.next_receipt_id);
+            self.next_receipt_id += 1;
+            send_frame = send_frame.header("receipt", &receipt_id);
+            Some((self.receipts.register(&receipt_id), receipt_id))
+        } else {
+            None
+        };
+        let send_frame = send_frame.headers(options.headers).body(body).build();
+
+        if let Err(e) = self.send_frame(&send_frame).await {
+            if let Some((_, receipt_id)) = &receipt {
+                self.receipts.cancel(receipt_id);
+            }
+            return Err(e);
+        }
+        match receipt {
+            Some((receipt, _)) => receipt.wait(self.receipt_timeout).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Starts a transaction, grouping sends and acknowledgements so they take effect together.
+    ///
+    /// The transaction can be used while messages are still being received from the client.
+    pub async fn begin(&mut self) -> Result<Transaction, PushPortError> {
+        let id = format!("tx-{}", self.next_transaction_id);
+        self.next_transaction_id += 1;
+
+        let begin_frame = FrameBuilder::new("BEGIN")
+            .header("transaction", &id)
+            .build();
+        self.send_frame(&begin_frame).await?;
+        Ok(Transaction::new(
+            id,
+            self.outbox.clone(),
+            self.receipts.clone(),
+            self.receipt_timeout,
+        ))
+    }
+
+    /// Disconnects gracefully.
+    ///
+    /// Sends a DISCONNECT frame with a `receipt` header and waits for the server's RECEIPT, up to the
+    /// configured receipt timeout, before closing the connection. The connection is closed even if the
+    /// receipt never arrives.
+    pub async fn disconnect(mut self) -> Result<(), PushPortError> {
+        let receipt_id = format!("disconnect-{}", self.next_receipt_id);
+        self.next_receipt_id += 1;
+        let receipt = self.receipts.register(&receipt_id);
+
+        let disconnect_frame = FrameBuilder::new("DISCONNECT")
+            .header("receipt", &receipt_id)
+            .build();
+        if let Err(e) = self.send_frame(&disconnect_frame).await {
+            self.receipts.cancel(&receipt_id);
+            return Err(e);
+        }
+        receipt.wait(self.receipt_timeout).await
+    }
+
+    /// Waits for the next message that is not claimed by a live [`Subscription`] handle.
+    ///
+    /// Returns `Ok(None)` once the server closes the connection. If the server negotiated heart-beats
+    /// and goes quiet for longer than the grace period allows, fails with
+    /// [`PushPortError::HeartbeatTimeout`]. ERROR frames from the server are returned as
+    /// [`PushPortError::ServerError`].
+    ///
+    /// Until this is first called, messages that do not fit in the
+    /// [`message_capacity`](ConnectOptions::message_capacity) buffer are dropped, so that messages
+    /// for dropped handles nobody reads cannot hold up the connection. After that, a full buffer
+    /// stops the client reading from the connection until it is drained.
+    pub async fn next_message(&mut self) -> Result<Option<Message>, PushPortError> {
+        self.inbox_read.store(true, Ordering::Relaxed);
+        self.inbox.recv().await.transpose()
+    }
+
+    /// Returns a stream yielding one decoded [`Message`] per MESSAGE frame that is not claimed by a
+    /// live [`Subscription`] handle.
+    ///
+    /// A message that cannot be decoded is yielded as an error and the stream carries on (see
+    /// [`PushPortError::is_message_error`]). The stream ends when the server closes the connection,
+    /// or after yielding an error that ends it, such as a lost connection or a server ERROR frame.
+    pub fn messages(&mut self) -> impl Stream<Item = Result<Message, PushPortError>> + '_ {
+        stream::unfold(Some(self), |client| async move {
+            let client = client?;
+            match client.next_message().await {
+                Ok(Some(message)) => Some((Ok(message), Some(client))),
+                Ok(None) => None,
+                Err(e) if e.is_message_error() => Some((Err(e), Some(client))),
+                Err(e) => Some((Err(e), None)),
+            }
+        })
     }
 
     /// Reads data from the connection, processes complete STOMP frames, and calls a provided callback with the message string.
     ///
-    /// The callback receives the decompressed message (or the raw body if decompression fails).
-    pub async fn read_messages<F>(&mut self, mut message_callback: F) -> Result<(), Box<dyn Error>>
+    /// The callback receives the message body, decompressed if it was gzipped. An error returned by the
+    /// callback stops reading and is returned as [`PushPortError::Callback`].
+    ///
+    /// Messages that cannot be decompressed are skipped. Use [`messages`](Self::messages) to see
+    /// those errors.
+    pub async fn read_messages<F>(&mut self, mut message_callback: F) -> Result<(), PushPortError>
     where
-        F: FnMut(String) -> Result<(), Box<dyn Error>>,
+        F: FnMut(String) -> Result<(), Box<dyn Error + Send + Sync>>,
     {
-        loop {
-            let mut buf = vec![0u8; 4096];
-            let n = self.stream.read(&mut buf).await?;
-            if n == 0 {
-                println!("Connection closed by server.");
-                break;
-            }
-            self.accumulated.extend_from_slice(&buf[..n]);
-
-            // Process every complete frame available.
-            while let Some((frame_len, frame)) = parse_stomp_frame(&self.accumulated) {
-                // Remove the processed frame from the accumulator.
-                self.accumulated.drain(..frame_len);
-
-                // Process the frame body:
-                let message = if frame.body.is_empty() {
-                    // No body means an empty message.
-                    String::new()
-                } else {
-                    // Attempt to decompress the body.
-                    match decompress_gzipped_data(&frame.body) {
-                        Ok(decompressed) => decompressed,
-                        Err(_) => {
-                            // If decompression fails, fallback to treating the body as plain text.
-                            String::from_utf8_lossy(&frame.body).to_string()
-                        }
-                    }
-                };
-                message_callback(message)?;
-            }
+        let mut messages = pin!(self.messages());
+        while let Some(message) = messages.next().await {
+            let message = match message {
+                Err(e) if e.is_message_error() => continue,
+                message => message?,
+            };
+            message_callback(message.into_body()).map_err(PushPortError::Callback)?;
+        }
+        Ok(())
+    }
+
+    /// Reads messages like [`read_messages`](Self::read_messages), but deserializes each one into a [`Pport`]
+    /// before handing it to the callback.
+    ///
+    /// Empty frames carry no document and are skipped, as are messages that cannot be decompressed
+    /// or deserialized.
+    pub async fn read_pport<F>(&mut self, mut pport_callback: F) -> Result<(), PushPortError>
+    where
+        F: FnMut(Pport) -> Result<(), Box<dyn Error + Send + Sync>>,
+    {
+        let mut messages = pin!(self.messages());
+        while let Some(message) = messages.next().await {
+            let pport = match message.and_then(|message| message.pport()) {
+                Err(e) if e.is_message_error() => continue,
+                pport => pport?,
+            };
+            pport_callback(pport).map_err(PushPortError::Callback)?;
         }
         Ok(())
     }
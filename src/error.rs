@@ -0,0 +1,235 @@
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Errors produced by the push port client.
+///
+/// All variants are `Send + Sync`, so errors can be moved across `tokio::spawn` boundaries.
+#[derive(Debug)]
+pub enum PushPortError {
+    /// Reading from or writing to the connection failed.
+    Io(std::io::Error),
+    /// The server closed the connection before the operation could complete.
+    ConnectionClosed,
+    /// Nothing was received from the server within the negotiated heart-beat window, so the
+    /// connection is presumed dead.
+    HeartbeatTimeout(Duration),
+    /// The server refused the CONNECT frame, replying with an ERROR frame.
+    HandshakeRejected {
+        /// The `message` header of the ERROR frame, if present.
+        message: Option<String>,
+        /// All headers of the ERROR frame, in the order they were received.
+        headers: Vec<(String, String)>,
+        /// The body of the ERROR frame.
+        body: String,
+    },
+    /// The server rejected the supplied username or password.
+    AuthenticationFailed {
+        /// The reason given by the server.
+        message: String,
+    },
+    /// The server sent an ERROR frame after the connection was established, e.g. because a
+    /// subscription was not permitted.
+    ServerError {
+        /// The `message` header of the ERROR frame, if present.
+        message: Option<String>,
+        /// All headers of the ERROR frame, in the order they were received.
+        headers: Vec<(String, String)>,
+        /// The body of the ERROR frame.
+        body: String,
+    },
+    /// A TLS connection could not be set up, e.g. because the server's certificate was not trusted.
+    Tls(String),
+    /// Data received from the server was not a valid STOMP frame.
+    FrameParse(String),
+    /// A message body looked gzipped but could not be decompressed.
+    Decompression(std::io::Error),
+    /// A message body could not be deserialized into the Darwin XML model.
+    Xml(quick_xml::DeError),
+    /// A subscription could not be set up.
+    SubscriptionFailed {
+        /// The destination that was being subscribed to.
+        destination: String,
+        /// Why the subscription failed.
+        reason: String,
+    },
+    /// The server did not confirm a frame with a RECEIPT in time.
+    ReceiptTimeout {
+        /// The receipt id that was requested.
+        receipt_id: String,
+    },
+    /// Connecting, including the STOMP handshake, did not finish within the configured
+    /// connect timeout.
+    ConnectTimeout(Duration),
+    /// Connection options could not be loaded, e.g. because an environment variable is missing.
+    Config(String),
+    /// A header could not be encoded in an outgoing frame.
+    InvalidHeader {
+        /// The name of the header.
+        name: String,
+        /// Why it could not be encoded.
+        reason: String,
+    },
+    /// A message callback returned an error.
+    Callback(Box<dyn Error + Send + Sync>),
+}
+
+impl fmt::Display for PushPortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushPortError::Io(e) => write!(f, "I/O error: {}", e),
+            PushPortError::ConnectionClosed => write!(f, "connection closed by server"),
+            PushPortError::HeartbeatTimeout(elapsed) => {
+                write!(f, "no data received from server for {:?}", elapsed)
+            }
+            PushPortError::HandshakeRejected { message, body, .. } => {
+                write!(f, "server rejected connection")?;
+                match message {
+                    Some(message) => write!(f, ": {}", message),
+                    None if !body.is_empty() => write!(f, ": {}", body.trim()),
+                    None => Ok(()),
+                }
+            }
+            PushPortError::AuthenticationFailed { message } => {
+                write!(f, "authentication failed: {}", message)
+            }
+            PushPortError::ServerError { message, body, .. } => {
+                write!(f, "server error")?;
+                match message {
+                    Some(message) => write!(f, ": {}", message),
+                    None if !body.is_empty() => write!(f, ": {}", body.trim()),
+                    None => Ok(()),
+                }
+            }
+            PushPortError::Tls(reason) => write!(f, "TLS error: {}", reason),
+            PushPortError::FrameParse(reason) => write!(f, "invalid STOMP frame: {}", reason),
+            PushPortError::Decompression(e) => write!(f, "failed to decompress message: {}", e),
+            PushPortError::Xml(e) => write!(f, "failed to decode XML message: {}", e),
+            PushPortError::SubscriptionFailed {
+                destination,
+                reason,
+            } => write!(f, "failed to subscribe to {}: {}", destination, reason),
+            PushPortError::ReceiptTimeout { receipt_id } => {
+                write!(f, "timed out waiting for receipt {}", receipt_id)
+            }
+            PushPortError::ConnectTimeout(limit) => {
+                write!(f, "timed out connecting after {:?}", limit)
+            }
+            PushPortError::Config(reason) => write!(f, "invalid configuration: {}", reason),
+            PushPortError::InvalidHeader { name, reason } => {
+                write!(f, "invalid {} header: {}", name, reason)
+            }
+            PushPortError::Callback(e) => write!(f, "message callback failed: {}", e),
+        }
+    }
+}
+
+impl PushPortError {
+    /// Returns `true` if the error means the connection can no longer be used, so reconnecting
+    /// may help.
+    ///
+    /// Rejected credentials are not included, since retrying them would fail the same way.
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(
+            self,
+            PushPortError::Io(_)
+                | PushPortError::ConnectionClosed
+                | PushPortError::HeartbeatTimeout(_)
+                | PushPortError::ConnectTimeout(_)
+                | PushPortError::HandshakeRejected { .. }
+                | PushPortError::FrameParse(_)
+        )
+    }
+
+    /// Returns `true` if the error only affects a single message, so the connection can still be
+    /// used for the messages after it.
+    pub fn is_message_error(&self) -> bool {
+        matches!(self, PushPortError::Decompression(_) | PushPortError::Xml(_))
+    }
+
+    /// Makes a copy of the error, for reporting one failure to several receivers.
+    ///
+    /// Wrapped I/O and callback errors cannot be cloned, so they are rebuilt from their kind and
+    /// message.
+    pub(crate) fn clone_lossy(&self) -> Self {
+        let copy_io = |e: &std::io::Error| std::io::Error::new(e.kind(), e.to_string());
+        match self {
+            PushPortError::Io(e) => PushPortError::Io(copy_io(e)),
+            PushPortError::ConnectionClosed => PushPortError::ConnectionClosed,
+            PushPortError::HeartbeatTimeout(elapsed) => PushPortError::HeartbeatTimeout(*elapsed),
+            PushPortError::HandshakeRejected {
+                message,
+                headers,
+                body,
+            } => PushPortError::HandshakeRejected {
+                message: message.clone(),
+                headers: headers.clone(),
+                body: body.clone(),
+            },
+            PushPortError::AuthenticationFailed { message } => {
+                PushPortError::AuthenticationFailed {
+                    message: message.clone(),
+                }
+            }
+            PushPortError::ServerError {
+                message,
+                headers,
+                body,
+            } => PushPortError::ServerError {
+                message: message.clone(),
+                headers: headers.clone(),
+                body: body.clone(),
+            },
+            PushPortError::Tls(reason) => PushPortError::Tls(reason.clone()),
+            PushPortError::FrameParse(reason) => PushPortError::FrameParse(reason.clone()),
+            PushPortError::Decompression(e) => PushPortError::Decompression(copy_io(e)),
+            PushPortError::Xml(e) => PushPortError::Xml(e.clone()),
+            PushPortError::SubscriptionFailed {
+                destination,
+                reason,
+            } => PushPortError::SubscriptionFailed {
+                destination: destination.clone(),
+                reason: reason.clone(),
+            },
+            PushPortError::ReceiptTimeout { receipt_id } => PushPortError::ReceiptTimeout {
+                receipt_id: receipt_id.clone(),
+            },
+            PushPortError::ConnectTimeout(limit) => PushPortError::ConnectTimeout(*limit),
+            PushPortError::Config(reason) => PushPortError::Config(reason.clone()),
+            PushPortError::InvalidHeader { name, reason } => PushPortError::InvalidHeader {
+                name: name.clone(),
+                reason: reason.clone(),
+            },
+            PushPortError::Callback(e) => PushPortError::Callback(e.to_string().into()),
+        }
+    }
+}
+
+impl Error for PushPortError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PushPortError::Io(e) | PushPortError::Decompression(e) => Some(e),
+            PushPortError::Xml(e) => Some(e),
+            PushPortError::Callback(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PushPortError {
+    fn from(e: std::io::Error) -> Self {
+        PushPortError::Io(e)
+    }
+}
+
+impl From<quick_xml::DeError> for PushPortError {
+    fn from(e: quick_xml::DeError) -> Self {
+        PushPortError::Xml(e)
+    }
+}
+
+// Fails to compile if a variant stops being safe to send between tasks.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<PushPortError>();
+};
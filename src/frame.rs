@@ -1,87 +1,339 @@
 use flate2::read::GzDecoder;
-use std::error::Error;
-use std::io::Read;
-
-/// Represents a complete STOMP frame with owned header and body.
-#[derive(Debug)]
-pub(crate) struct StompFrame {
-    pub headers: String,
-    pub body: Vec<u8>,
-}
-
-/// Looks for a header line starting with "content-length:" and returns its value.
-fn get_content_length(headers: &str) -> Option<usize> {
-    headers
-        .lines()
-        .find_map(|line| {
-            line.strip_prefix("content-length:")
-                .and_then(|s| s.trim().parse().ok())
-        })
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+use crate::error::PushPortError;
+
+/// Represents a complete STOMP frame with owned command, headers and body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StompFrame {
+    pub(crate) command: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl StompFrame {
+    /// Returns the command on the first line of the frame, e.g. "CONNECTED" or "MESSAGE".
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Returns the value of the first header with the given name.
+    ///
+    /// STOMP 1.2 allows a header to be repeated, in which case only the first occurrence counts.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find_map(|(key, value)| (key == name).then_some(value.as_str()))
+    }
+
+    /// Returns every header in the order it was received, including repeats, with escapes decoded.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Returns the raw body of the frame.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Checks that every header can be encoded.
+    ///
+    /// Escapes are not available in CONNECT and CONNECTED frames, so a line break in one of their
+    /// headers would split it in two.
+    pub(crate) fn validate(&self) -> Result<(), PushPortError> {
+        if uses_escapes(&self.command) {
+            return Ok(());
+        }
+        match self
+            .headers
+            .iter()
+            .find(|(name, value)| [name, value].iter().any(|text| text.contains(['\r', '\n'])))
+        {
+            Some((name, _)) => Err(PushPortError::InvalidHeader {
+                name: name.clone(),
+                reason: format!(
+                    "{} frames cannot contain line breaks in headers",
+                    self.command
+                ),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Encodes the frame for sending.
+    ///
+    /// Header names and values are escaped as STOMP 1.2 requires, except in CONNECT and CONNECTED
+    /// frames, which are exempt. A `content-length` header matching the body is added whenever
+    /// the body is not empty, replacing any given explicitly.
+    pub fn encode(&self) -> Vec<u8> {
+        let escaped = uses_escapes(&self.command);
+        let encode = |text: &str| {
+            if escaped {
+                escape(text)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let mut head = format!("{}\n", self.command);
+        for (name, value) in &self.headers {
+            if name == "content-length" {
+                continue;
+            }
+            head.push_str(&format!("{}:{}\n", encode(name), encode(value)));
+        }
+        if !self.body.is_empty() {
+            head.push_str(&format!("content-length:{}\n", self.body.len()));
+        }
+        head.push('\n');
+
+        let mut encoded = head.into_bytes();
+        encoded.extend_from_slice(&self.body);
+        encoded.push(0);
+        encoded
+    }
+}
+
+/// Builds outgoing STOMP frames.
+///
+/// ```
+/// use national_rail_push_port_client::FrameBuilder;
+///
+/// let frame = FrameBuilder::new("SEND")
+///     .header("destination", "/queue/replay")
+///     .body(b"<Pport/>".to_vec())
+///     .build();
+/// assert_eq!(frame.header("content-length"), None);
+/// assert!(frame.encode().starts_with(b"SEND\ndestination:/queue/replay\ncontent-length:8\n\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameBuilder {
+    frame: StompFrame,
+}
+
+impl FrameBuilder {
+    /// Starts a frame with the given command, e.g. "SUBSCRIBE".
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            frame: StompFrame {
+                command: command.into(),
+                headers: Vec::new(),
+                body: Vec::new(),
+            },
+        }
+    }
+
+    /// Adds a header. Headers are sent in the order they are added.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.frame.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Adds several headers, in order.
+    pub fn headers<I, K, V>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.frame
+            .headers
+            .extend(headers.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Sets the body. It may contain arbitrary bytes, including nulls.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.frame.body = body.into();
+        self
+    }
+
+    /// Finishes the frame.
+    pub fn build(self) -> StompFrame {
+        self.frame
+    }
+}
+
+/// CONNECT and CONNECTED frames are exempt from escaping, for compatibility with STOMP 1.0.
+fn uses_escapes(command: &str) -> bool {
+    !matches!(command, "CONNECT" | "CONNECTED")
+}
+
+/// Encodes the STOMP 1.2 header escapes `\r`, `\n`, `\c` and `\\`.
+fn escape(raw: &str) -> String {
+    let mut escaped = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        match c {
+            '\r' => escaped.push_str("\\r"),
+            '\n' => escaped.push_str("\\n"),
+            ':' => escaped.push_str("\\c"),
+            '\\' => escaped.push_str("\\\\"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns the value of the content-length header, if the frame has one.
+fn get_content_length(headers: &[(String, String)]) -> Result<Option<usize>, PushPortError> {
+    let Some((_, value)) = headers.iter().find(|(name, _)| name == "content-length") else {
+        return Ok(None);
+    };
+    value
+        .trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| PushPortError::FrameParse(format!("invalid content-length: {}", value)))
 }
 
 /// Parses a complete STOMP frame from `data` and returns a tuple:
 /// (total number of bytes consumed, parsed StompFrame).
 ///
 /// A frame is defined as:
-/// - Headers terminated by "\n\n", followed by either:
+/// - Any number of EOLs (heart-beats or padding after the previous frame), which are skipped.
+/// - A command line and header lines, each ending in "\n" or "\r\n", and a blank line, followed by either:
 ///   - A body of length given by a content-length header and a trailing null byte, or
 ///   - A body terminated by a null byte.
-/// Returns `None` if a complete frame isn’t yet available.
-pub(crate) fn parse_stomp_frame(data: &[u8]) -> Option<(usize, StompFrame)> {
-    let (header_len, header_end) = find_header_end(data)?;
-    let headers = String::from_utf8_lossy(&data[..header_len]).to_string();
-    parse_body(data, header_end, &headers)
+///
+/// Returns `Ok(None)` if a complete frame isn’t yet available, and an error if the data can never
+/// form a valid frame.
+pub(crate) fn parse_stomp_frame(data: &[u8]) -> Result<Option<(usize, StompFrame)>, PushPortError> {
+    let start = skip_eols(data);
+    let Some((header_len, header_end)) = find_header_end(&data[start..]) else {
+        return Ok(None);
+    };
+    let (command, headers) = parse_headers(&data[start..start + header_len])?;
+    let parsed = parse_body(&data[start..], header_end, command, headers)?;
+    Ok(parsed.map(|(frame_len, frame)| (start + frame_len, frame)))
+}
+
+/// Returns the number of leading bytes that are bare "\n" or "\r\n" EOLs.
+pub(crate) fn skip_eols(data: &[u8]) -> usize {
+    let mut pos = 0;
+    loop {
+        match &data[pos..] {
+            [b'\n', ..] => pos += 1,
+            [b'\r', b'\n', ..] => pos += 2,
+            _ => return pos,
+        }
+    }
 }
 
+/// Finds the blank line that ends the headers, returning the length of the command and header
+/// lines and the offset at which the body starts.
 fn find_header_end(data: &[u8]) -> Option<(usize, usize)> {
-    let sep = b"\n\n";
-    let pos = data.windows(2).position(|w| w == sep)?;
-    Some((pos, pos + sep.len()))
+    data.iter()
+        .enumerate()
+        .filter(|&(_, &b)| b == b'\n')
+        .find_map(|(pos, _)| match &data[pos + 1..] {
+            [b'\n', ..] => Some((pos, pos + 2)),
+            [b'\r', b'\n', ..] => Some((pos, pos + 3)),
+            _ => None,
+        })
+}
+
+/// Splits the header section into the command and an ordered list of headers.
+fn parse_headers(data: &[u8]) -> Result<(String, Vec<(String, String)>), PushPortError> {
+    let text = std::str::from_utf8(data)
+        .map_err(|_| PushPortError::FrameParse("headers are not valid UTF-8".to_string()))?;
+    // Lines may end in "\r\n"; a carriage return inside a value is always escaped.
+    let mut lines = text
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line));
+    let command = lines.next().unwrap_or_default().to_string();
+    let escaped = uses_escapes(&command);
+
+    let headers = lines
+        .map(|line| {
+            let (name, value) = line.split_once(':').ok_or_else(|| {
+                PushPortError::FrameParse(format!("header line without a colon: {}", line))
+            })?;
+            if escaped {
+                Ok((unescape(name)?, unescape(value)?))
+            } else {
+                Ok((name.to_string(), value.to_string()))
+            }
+        })
+        .collect::<Result<_, PushPortError>>()?;
+    Ok((command, headers))
 }
 
-fn parse_body(data: &[u8], header_end: usize, headers: &str) -> Option<(usize, StompFrame)> {
-    if let Some(len) = get_content_length(headers) {
-        parse_fixed_length_body(data, header_end, len, headers)
-    } else {
-        parse_null_terminated_body(data, header_end, headers)
+/// Decodes the STOMP 1.2 header escapes `\r`, `\n`, `\c` and `\\`.
+fn unescape(raw: &str) -> Result<String, PushPortError> {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            decoded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('r') => decoded.push('\r'),
+            Some('n') => decoded.push('\n'),
+            Some('c') => decoded.push(':'),
+            Some('\\') => decoded.push('\\'),
+            other => {
+                return Err(PushPortError::FrameParse(format!(
+                    "invalid escape sequence in header: \\{}",
+                    other.map(String::from).unwrap_or_default()
+                )))
+            }
+        }
     }
+    Ok(decoded)
 }
 
-fn parse_fixed_length_body(
+fn parse_body(
     data: &[u8],
     header_end: usize,
-    body_length: usize,
-    headers: &str,
-) -> Option<(usize, StompFrame)> {
-    let total_length = header_end + body_length + 1;
-    if data.len() < total_length {
-        return None;
-    }
-    let body = data[header_end..header_end + body_length].to_vec();
-    Some((
+    command: String,
+    headers: Vec<(String, String)>,
+) -> Result<Option<(usize, StompFrame)>, PushPortError> {
+    let (total_length, body) = match get_content_length(&headers)? {
+        Some(len) => match parse_fixed_length_body(data, header_end, len)? {
+            Some(parsed) => parsed,
+            None => return Ok(None),
+        },
+        None => match parse_null_terminated_body(data, header_end) {
+            Some(parsed) => parsed,
+            None => return Ok(None),
+        },
+    };
+    Ok(Some((
         total_length,
         StompFrame {
-            headers: headers.to_string(),
+            command,
+            headers,
             body,
         },
-    ))
+    )))
 }
 
-fn parse_null_terminated_body(
+fn parse_fixed_length_body(
     data: &[u8],
     header_end: usize,
-    headers: &str,
-) -> Option<(usize, StompFrame)> {
+    body_length: usize,
+) -> Result<Option<(usize, Vec<u8>)>, PushPortError> {
+    let total_length = header_end + body_length + 1;
+    if data.len() < total_length {
+        return Ok(None);
+    }
+    if data[total_length - 1] != 0 {
+        return Err(PushPortError::FrameParse(
+            "body is not followed by a null byte".to_string(),
+        ));
+    }
+    let body = data[header_end..header_end + body_length].to_vec();
+    Ok(Some((total_length, body)))
+}
+
+fn parse_null_terminated_body(data: &[u8], header_end: usize) -> Option<(usize, Vec<u8>)> {
     let null_pos = data[header_end..].iter().position(|&b| b == 0)?;
     let total_length = header_end + null_pos + 1;
     let body = data[header_end..header_end + null_pos].to_vec();
-    Some((
-        total_length,
-        StompFrame {
-            headers: headers.to_string(),
-            body,
-        },
-    ))
+    Some((total_length, body))
 }
 
 /// Decompresses gzipped data into a String using GzDecoder.
@@ -92,3 +344,116 @@ pub(crate) fn decompress_gzipped_data(compressed: &[u8]) -> Result<String, std::
     gz.read_to_string(&mut decompressed)?;
     Ok(decompressed)
 }
+
+/// Compresses data with gzip, producing bodies that [`decompress_gzipped_data`] can read back.
+pub(crate) fn compress_gzipped_data(data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(data)?;
+    gz.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_complete(data: &[u8]) -> StompFrame {
+        let (len, frame) = parse_stomp_frame(data).unwrap().expect("a complete frame");
+        assert_eq!(len, data.len());
+        frame
+    }
+
+    #[test]
+    fn first_occurrence_of_a_repeated_header_wins() {
+        let frame = parse_complete(b"MESSAGE\nfoo:first\nfoo:second\n\n\0");
+        assert_eq!(frame.header("foo"), Some("first"));
+        assert_eq!(
+            frame.headers(),
+            [
+                ("foo".to_string(), "first".to_string()),
+                ("foo".to_string(), "second".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn header_escapes_are_decoded() {
+        let frame = parse_complete(b"MESSAGE\na\\cb:line\\none\\rtwo\\\\\n\n\0");
+        assert_eq!(frame.header("a:b"), Some("line\none\rtwo\\"));
+    }
+
+    #[test]
+    fn undefined_and_trailing_escapes_are_rejected() {
+        assert!(matches!(
+            parse_stomp_frame(b"MESSAGE\nfoo:a\\tb\n\n\0"),
+            Err(PushPortError::FrameParse(_))
+        ));
+        assert!(matches!(
+            parse_stomp_frame(b"MESSAGE\nfoo:bar\\\n\n\0"),
+            Err(PushPortError::FrameParse(_))
+        ));
+    }
+
+    #[test]
+    fn connected_headers_are_not_unescaped() {
+        let frame = parse_complete(b"CONNECTED\nserver:broker\\c1\\t\n\n\0");
+        assert_eq!(frame.header("server"), Some("broker\\c1\\t"));
+    }
+
+    #[test]
+    fn header_lines_without_a_colon_are_rejected() {
+        assert!(matches!(
+            parse_stomp_frame(b"MESSAGE\nnot-a-header\n\n\0"),
+            Err(PushPortError::FrameParse(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_content_length_is_rejected() {
+        assert!(matches!(
+            parse_stomp_frame(b"MESSAGE\ncontent-length:ten\n\n0123456789\0"),
+            Err(PushPortError::FrameParse(_))
+        ));
+    }
+
+    #[test]
+    fn content_length_bodies_may_contain_null_bytes() {
+        let frame = parse_complete(b"MESSAGE\ncontent-length:3\n\na\0b\0");
+        assert_eq!(frame.body(), b"a\0b");
+    }
+
+    #[test]
+    fn content_length_bodies_must_be_followed_by_a_null_byte() {
+        assert!(matches!(
+            parse_stomp_frame(b"MESSAGE\ncontent-length:3\n\nabcd\0"),
+            Err(PushPortError::FrameParse(_))
+        ));
+        // Too short to tell yet.
+        assert!(parse_stomp_frame(b"MESSAGE\ncontent-length:3\n\nabc")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn crlf_line_endings_are_accepted() {
+        let frame = parse_complete(b"MESSAGE\r\nfoo:bar\r\n\r\nbody\0");
+        assert_eq!(frame.command(), "MESSAGE");
+        assert_eq!(frame.header("foo"), Some("bar"));
+        assert_eq!(frame.body(), b"body");
+    }
+
+    #[test]
+    fn leading_eols_are_skipped() {
+        let frame = parse_complete(b"\n\r\n\nMESSAGE\nfoo:bar\n\n\0");
+        assert_eq!(frame.command(), "MESSAGE");
+        assert_eq!(skip_eols(b"\n\r\nMESSAGE"), 3);
+    }
+
+    #[test]
+    fn heart_beat_split_between_reads_waits_for_the_rest() {
+        // A "\r\n" heart-beat whose "\n" has not arrived yet.
+        assert_eq!(skip_eols(b"\r"), 0);
+        assert!(parse_stomp_frame(b"\r").unwrap().is_none());
+        let frame = parse_complete(b"\r\nMESSAGE\n\n\0");
+        assert_eq!(frame.command(), "MESSAGE");
+    }
+}
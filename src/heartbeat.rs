@@ -0,0 +1,133 @@
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::timeout;
+
+/// The queue of encoded frames waiting to be written by [`run_writer`].
+pub(crate) type Outbox = mpsc::UnboundedSender<Vec<u8>>;
+
+/// Heart-beat intervals agreed between the client and the server.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Heartbeat {
+    /// How often the client must send something, if at all.
+    pub send: Option<Duration>,
+    /// How long the client may go without hearing from the server before giving up, if at all.
+    pub receive_timeout: Option<Duration>,
+}
+
+impl Heartbeat {
+    /// Negotiates heart-beat intervals as described in the STOMP 1.2 specification.
+    ///
+    /// `client` is the (cx, cy) pair the client offered and `server` the (sx, sy) pair the server
+    /// replied with, both in milliseconds. Each direction is disabled if either side sent 0, and
+    /// otherwise uses the larger of the two values. The receive timeout is that value times `grace`,
+    /// which never shortens it and saturates rather than overflowing.
+    pub fn negotiate(client: (u64, u64), server: (u64, u64), grace: f64) -> Self {
+        let (cx, cy) = client;
+        let (sx, sy) = server;
+        let interval = |a: u64, b: u64| (a != 0 && b != 0).then(|| Duration::from_millis(a.max(b)));
+        Self {
+            send: interval(cx, sy),
+            receive_timeout: interval(sx, cy).map(|d| {
+                Duration::try_from_secs_f64(d.as_secs_f64() * grace.max(1.0))
+                    .unwrap_or(Duration::MAX)
+            }),
+        }
+    }
+}
+
+/// Writes outgoing frames to the connection, sending a heart-beat EOL whenever nothing else has been
+/// written for the negotiated send interval.
+///
+/// Runs until every sender for `outbox` has been dropped, `shutdown` resolves or a write fails. A
+/// failed write is passed to `failed`, so that the reader can end the connection with it.
+pub(crate) async fn run_writer<W>(
+    mut writer: W,
+    mut outbox: mpsc::UnboundedReceiver<Vec<u8>>,
+    send_interval: Option<Duration>,
+    mut shutdown: oneshot::Receiver<()>,
+    failed: oneshot::Sender<std::io::Error>,
+) where
+    W: AsyncWrite + Unpin,
+{
+    loop {
+        let recv = async {
+            match send_interval {
+                Some(interval) => timeout(interval, outbox.recv()).await,
+                None => Ok(outbox.recv().await),
+            }
+        };
+        let next = tokio::select! {
+            next = recv => next,
+            _ = &mut shutdown => break,
+        };
+        let bytes = match next {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break,
+            // Nothing was sent for a whole interval, so send a heart-beat instead.
+            Err(_) => b"\n".to_vec(),
+        };
+        if let Err(e) = writer.write_all(&bytes).await {
+            // The reader may already have gone.
+            let _ = failed.send(e);
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_on_either_side_disables_that_direction() {
+        let disabled = Heartbeat {
+            send: None,
+            receive_timeout: None,
+        };
+        assert_eq!(Heartbeat::negotiate((0, 0), (0, 0), 2.0), disabled);
+        assert_eq!(Heartbeat::negotiate((1000, 1000), (0, 0), 2.0), disabled);
+        assert_eq!(Heartbeat::negotiate((0, 0), (1000, 1000), 2.0), disabled);
+
+        let send_only = Heartbeat::negotiate((1000, 0), (1000, 500), 2.0);
+        assert_eq!(send_only.send, Some(Duration::from_millis(1000)));
+        assert_eq!(send_only.receive_timeout, None);
+        let receive_only = Heartbeat::negotiate((1000, 500), (1000, 0), 2.0);
+        assert_eq!(receive_only.send, None);
+        assert_eq!(
+            receive_only.receive_timeout,
+            Some(Duration::from_millis(2000))
+        );
+    }
+
+    #[test]
+    fn each_direction_uses_the_larger_interval() {
+        let heartbeat = Heartbeat::negotiate((1000, 2000), (3000, 500), 1.0);
+        assert_eq!(heartbeat.send, Some(Duration::from_millis(1000)));
+        assert_eq!(heartbeat.receive_timeout, Some(Duration::from_millis(3000)));
+
+        let heartbeat = Heartbeat::negotiate((500, 4000), (3000, 1500), 1.0);
+        assert_eq!(heartbeat.send, Some(Duration::from_millis(1500)));
+        assert_eq!(heartbeat.receive_timeout, Some(Duration::from_millis(4000)));
+    }
+
+    #[test]
+    fn grace_multiplies_the_receive_timeout_but_never_shortens_it() {
+        let heartbeat = Heartbeat::negotiate((1000, 1000), (1000, 1000), 2.5);
+        assert_eq!(heartbeat.send, Some(Duration::from_millis(1000)));
+        assert_eq!(heartbeat.receive_timeout, Some(Duration::from_millis(2500)));
+
+        let heartbeat = Heartbeat::negotiate((1000, 1000), (1000, 1000), 0.5);
+        assert_eq!(heartbeat.receive_timeout, Some(Duration::from_millis(1000)));
+        let heartbeat = Heartbeat::negotiate((1000, 1000), (1000, 1000), f64::NAN);
+        assert_eq!(heartbeat.receive_timeout, Some(Duration::from_millis(1000)));
+    }
+
+    #[test]
+    fn huge_or_infinite_grace_saturates() {
+        for grace in [f64::INFINITY, f64::MAX, 1e300] {
+            let heartbeat = Heartbeat::negotiate((1000, 1000), (1000, 1000), grace);
+            assert_eq!(heartbeat.receive_timeout, Some(Duration::MAX));
+        }
+    }
+}
@@ -1,4 +1,32 @@
 pub mod client;
+mod error;
 mod frame;
+mod heartbeat;
+mod message;
+pub mod models;
+mod options;
+mod reader;
+mod receipt;
+mod reconnect;
+mod subscription;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tls")]
+mod tls;
+mod transaction;
+mod transport;
 
-pub use client::NationalRailPushPortClient;
\ No newline at end of file
+pub use client::{ConnectionInfo, NationalRailPushPortClient};
+pub use error::PushPortError;
+pub use frame::{FrameBuilder, StompFrame};
+pub use message::Message;
+pub use models::Pport;
+pub use options::{AckMode, ConnectOptions, SendOptions, SubscribeOptions};
+pub use reconnect::{Backoff, ConnectionEvent, ReconnectingClient};
+pub use subscription::Subscription;
+#[cfg(feature = "tls")]
+pub use tls::TlsOptions;
+#[cfg(feature = "tls")]
+pub use tokio_rustls::rustls;
+pub use transaction::Transaction;
+pub use transport::Transport;
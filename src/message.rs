@@ -0,0 +1,134 @@
+use crate::error::PushPortError;
+use crate::frame::{decompress_gzipped_data, FrameBuilder, StompFrame};
+use crate::heartbeat::Outbox;
+use crate::models::Pport;
+
+/// The first two bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A message received from the push port, with its body decoded to text.
+#[derive(Debug, Clone)]
+pub struct Message {
+    headers: Vec<(String, String)>,
+    body: String,
+    outbox: Option<Outbox>,
+}
+
+impl Message {
+    /// Decodes the body of a frame, decompressing it if it is gzipped.
+    ///
+    /// Bodies without the gzip magic number are treated as plain text.
+    pub(crate) fn from_frame(frame: StompFrame) -> Result<Self, PushPortError> {
+        let body = if frame.body.is_empty() {
+            // No body means an empty message.
+            String::new()
+        } else if frame.body.starts_with(&GZIP_MAGIC) {
+            decompress_gzipped_data(&frame.body).map_err(PushPortError::Decompression)?
+        } else {
+            String::from_utf8_lossy(&frame.body).to_string()
+        };
+        Ok(Self {
+            headers: frame.headers,
+            body,
+            outbox: None,
+        })
+    }
+
+    /// Attaches the connection the message arrived on, so it can be acknowledged. Only messages
+    /// for subscriptions that acknowledge are given one.
+    pub(crate) fn with_outbox(mut self, outbox: Outbox) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    /// Returns every header of the MESSAGE frame, in the order they were received.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Returns the value of the first header with the given name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find_map(|(key, value)| (key == name).then_some(value.as_str()))
+    }
+
+    /// The destination the message was sent to.
+    pub fn destination(&self) -> Option<&str> {
+        self.header("destination")
+    }
+
+    /// The id of the subscription the message was delivered for.
+    pub fn subscription(&self) -> Option<&str> {
+        self.header("subscription")
+    }
+
+    /// The server-assigned message id.
+    pub fn message_id(&self) -> Option<&str> {
+        self.header("message-id")
+    }
+
+    /// The message body, decompressed if it was gzipped.
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// Consumes the message, returning its body.
+    pub fn into_body(self) -> String {
+        self.body
+    }
+
+    /// The id to use when acknowledging the message, present for subscriptions that do not use
+    /// [`AckMode::Auto`](crate::AckMode::Auto).
+    pub fn ack_id(&self) -> Option<&str> {
+        self.header("ack")
+    }
+
+    /// Acknowledges the message, telling the server it has been processed.
+    ///
+    /// With [`AckMode::Client`](crate::AckMode::Client) this also acknowledges every earlier message
+    /// on the subscription. Does nothing for messages from auto-acknowledged subscriptions.
+    ///
+    /// Messages are identified by their `ack` header, or by their `message-id` and `subscription`
+    /// headers if there is none, as with STOMP 1.1 servers. Fails with
+    /// [`PushPortError::InvalidHeader`] if the message has neither.
+    pub fn ack(&self) -> Result<(), PushPortError> {
+        self.send_ack("ACK")
+    }
+
+    /// Tells the server the message was not processed, so it can be redelivered or dead-lettered.
+    ///
+    /// Does nothing for messages from auto-acknowledged subscriptions. Messages are identified as
+    /// for [`ack`](Self::ack).
+    pub fn nack(&self) -> Result<(), PushPortError> {
+        self.send_ack("NACK")
+    }
+
+    fn send_ack(&self, command: &str) -> Result<(), PushPortError> {
+        let Some(outbox) = &self.outbox else {
+            return Ok(());
+        };
+        let frame = match (self.ack_id(), self.message_id(), self.subscription()) {
+            (Some(ack_id), _, _) => FrameBuilder::new(command).header("id", ack_id),
+            (None, Some(message_id), Some(subscription)) => FrameBuilder::new(command)
+                .header("message-id", message_id)
+                .header("subscription", subscription),
+            _ => {
+                return Err(PushPortError::InvalidHeader {
+                    name: "id".to_string(),
+                    reason: "the message has no ack header, nor message-id and subscription"
+                        .to_string(),
+                })
+            }
+        }
+        .build();
+        outbox
+            .send(frame.encode())
+            .map_err(|_| PushPortError::ConnectionClosed)
+    }
+
+    /// Deserializes the body into a [`Pport`] document.
+    pub fn pport(&self) -> Result<Pport, PushPortError> {
+        Ok(Pport::from_xml(&self.body)?)
+    }
+}
@@ -0,0 +1,102 @@
+use serde::Deserialize;
+
+/// A `trainAlert` message, an older form of free-text alert about one or more services.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TrainAlert {
+    /// Unique identifier of the alert.
+    #[serde(rename = "AlertID")]
+    pub alert_id: String,
+    /// The services the alert applies to.
+    #[serde(rename = "AlertServices", default)]
+    pub alert_services: AlertServices,
+    /// Whether the alert should be sent by SMS.
+    #[serde(rename = "SendAlertBySMS", default)]
+    pub send_alert_by_sms: bool,
+    /// Whether the alert should be sent by email.
+    #[serde(rename = "SendAlertByEmail", default)]
+    pub send_alert_by_email: bool,
+    /// Whether the alert should be sent by Twitter.
+    #[serde(rename = "SendAlertByTwitter", default)]
+    pub send_alert_by_twitter: bool,
+    /// The source of the alert.
+    #[serde(rename = "Source")]
+    pub source: String,
+    /// The text of the alert.
+    #[serde(rename = "AlertText")]
+    pub alert_text: String,
+    /// Who the alert is intended for, e.g. `Customer`, `Staff` or `Operations`.
+    #[serde(rename = "Audience")]
+    pub audience: String,
+    /// The type of alert, e.g. `Normal` or `Forced`.
+    #[serde(rename = "AlertType")]
+    pub alert_type: String,
+}
+
+/// The list of services a train alert applies to.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct AlertServices {
+    /// The services.
+    #[serde(rename = "AlertService", default)]
+    pub services: Vec<AlertService>,
+}
+
+/// A service a train alert applies to.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AlertService {
+    /// RTTI unique train identifier.
+    #[serde(rename = "@RID")]
+    pub rid: String,
+    /// Train UID.
+    #[serde(rename = "@UID")]
+    pub uid: String,
+    /// Scheduled start date, in `YYYY-MM-DD` format.
+    #[serde(rename = "@SSD")]
+    pub ssd: String,
+    /// The CRS codes of the locations the alert applies at.
+    #[serde(rename = "Location", default)]
+    pub locations: Vec<String>,
+}
+
+/// An `alarm` message, raised by Darwin when one of its data feeds fails.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Alarm {
+    /// Whether the alarm is being raised or cleared.
+    #[serde(rename = "$value")]
+    pub action: AlarmAction,
+}
+
+/// The change an alarm message makes.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum AlarmAction {
+    /// Raises a new alarm.
+    #[serde(rename = "set")]
+    Set(AlarmSet),
+    /// Clears the alarm with the given identifier.
+    #[serde(rename = "clear")]
+    Clear(u32),
+}
+
+/// A newly raised alarm.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AlarmSet {
+    /// Unique identifier of the alarm.
+    #[serde(rename = "@id")]
+    pub id: u32,
+    /// What has failed.
+    #[serde(rename = "$value")]
+    pub kind: AlarmKind,
+}
+
+/// The kind of failure an alarm reports.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum AlarmKind {
+    /// A TD area has failed, identified by its area code.
+    #[serde(rename = "tdAreaFail")]
+    TdAreaFail(String),
+    /// The whole TD feed has failed.
+    #[serde(rename = "tdFeedFail")]
+    TdFeedFail(String),
+    /// The Tyrell feed has failed.
+    #[serde(rename = "tyrellFeedFail")]
+    TyrellFeedFail(String),
+}
@@ -0,0 +1,62 @@
+use serde::Deserialize;
+
+/// An `association` message, linking two services at a location.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Association {
+    /// TIPLOC of the location where the association applies.
+    #[serde(rename = "@tiploc")]
+    pub tiploc: String,
+    /// The kind of association.
+    #[serde(rename = "@category")]
+    pub category: AssociationCategory,
+    /// Whether the association has been cancelled.
+    #[serde(rename = "@isCancelled", default)]
+    pub is_cancelled: bool,
+    /// Whether the association has been deleted and should no longer be used.
+    #[serde(rename = "@isDeleted", default)]
+    pub is_deleted: bool,
+    /// The main (through, previous working or link-from) service.
+    pub main: AssociatedService,
+    /// The associated (starting, terminating, subsequent working or link-to) service.
+    pub assoc: AssociatedService,
+}
+
+/// The kind of association between two services.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum AssociationCategory {
+    /// The associated service joins the main service.
+    #[serde(rename = "JJ")]
+    Join,
+    /// The associated service divides from the main service.
+    #[serde(rename = "VV")]
+    Divide,
+    /// The associated service is the next working of the main service.
+    #[serde(rename = "NP")]
+    NextWorking,
+    /// The two services are linked.
+    #[serde(rename = "LK")]
+    Link,
+}
+
+/// A reference to one of the services in an association.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AssociatedService {
+    /// RTTI unique train identifier.
+    #[serde(rename = "@rid")]
+    pub rid: String,
+    /// Working scheduled time of arrival at the association location.
+    #[serde(rename = "@wta")]
+    pub wta: Option<String>,
+    /// Working scheduled time of departure from the association location.
+    #[serde(rename = "@wtd")]
+    pub wtd: Option<String>,
+    /// Working scheduled time of passing the association location.
+    #[serde(rename = "@wtp")]
+    pub wtp: Option<String>,
+    /// Public scheduled time of arrival at the association location.
+    #[serde(rename = "@pta")]
+    pub pta: Option<String>,
+    /// Public scheduled time of departure from the association location.
+    #[serde(rename = "@ptd")]
+    pub ptd: Option<String>,
+}
@@ -0,0 +1,127 @@
+use serde::Deserialize;
+
+/// A `scheduleFormations` message, listing the coach formations a service will run with.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScheduleFormations {
+    /// RTTI unique train identifier.
+    #[serde(rename = "@rid")]
+    pub rid: String,
+    /// The formations of the service. Different parts of the journey may use different formations.
+    #[serde(rename = "formation", default)]
+    pub formations: Vec<Formation>,
+}
+
+/// A single formation of coaches.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Formation {
+    /// Unique identifier of the formation, referenced by loading messages.
+    #[serde(rename = "@fid")]
+    pub fid: String,
+    /// The source of the formation data.
+    #[serde(rename = "@src")]
+    pub src: Option<String>,
+    /// The coaches in the formation, from the front of the train.
+    pub coaches: Coaches,
+}
+
+/// The list of coaches in a formation.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Coaches {
+    /// The coaches, from the front of the train.
+    #[serde(rename = "coach", default)]
+    pub coaches: Vec<Coach>,
+}
+
+/// A single coach in a formation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Coach {
+    /// The number or letter identifying the coach, e.g. `A` or `12`.
+    #[serde(rename = "@coachNumber")]
+    pub coach_number: String,
+    /// The class of the coach, e.g. `First`, `Standard` or `Mixed`.
+    #[serde(rename = "@coachClass")]
+    pub coach_class: Option<String>,
+    /// The toilet facilities in the coach, if known.
+    pub toilet: Option<Toilet>,
+}
+
+/// The toilet facilities in a coach.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Toilet {
+    /// The type of toilet.
+    #[serde(rename = "$text")]
+    pub kind: ToiletType,
+    /// Whether the toilet is in service.
+    #[serde(rename = "@status")]
+    pub status: Option<ToiletStatus>,
+}
+
+/// The type of toilet in a coach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ToiletType {
+    /// The coach's toilet facilities are not known.
+    Unknown,
+    /// The coach has no toilet.
+    None,
+    /// A standard toilet.
+    Standard,
+    /// An accessible toilet, suitable for wheelchair users.
+    Accessible,
+}
+
+/// Whether a toilet is in service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ToiletStatus {
+    /// Whether the toilet is in service is not known.
+    Unknown,
+    /// The toilet is in service.
+    InService,
+    /// The toilet is out of service.
+    NotInService,
+}
+
+/// A `formationLoading` message, giving how busy each coach is at a location.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FormationLoading {
+    /// The formation the loading applies to.
+    #[serde(rename = "@fid")]
+    pub fid: String,
+    /// RTTI unique train identifier.
+    #[serde(rename = "@rid")]
+    pub rid: String,
+    /// TIPLOC of the location the loading applies at.
+    #[serde(rename = "@tpl")]
+    pub tpl: String,
+    /// Working scheduled time of arrival.
+    #[serde(rename = "@wta")]
+    pub wta: Option<String>,
+    /// Working scheduled time of departure.
+    #[serde(rename = "@wtd")]
+    pub wtd: Option<String>,
+    /// Working scheduled time of passing.
+    #[serde(rename = "@wtp")]
+    pub wtp: Option<String>,
+    /// Public scheduled time of arrival.
+    #[serde(rename = "@pta")]
+    pub pta: Option<String>,
+    /// Public scheduled time of departure.
+    #[serde(rename = "@ptd")]
+    pub ptd: Option<String>,
+    /// The loading of each coach.
+    #[serde(rename = "loading", default)]
+    pub loading: Vec<CoachLoading>,
+}
+
+/// The loading of a single coach.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CoachLoading {
+    /// The number or letter identifying the coach.
+    #[serde(rename = "@coachNumber")]
+    pub coach_number: String,
+    /// The source of the loading data.
+    #[serde(rename = "@src")]
+    pub src: Option<String>,
+    /// How full the coach is, as a percentage.
+    #[serde(rename = "$text")]
+    pub percentage: u8,
+}
@@ -0,0 +1,122 @@
+//! Typed models for the Darwin Push Port XML feed.
+//!
+//! Every message on the feed is a `Pport` document carrying either an update (`uR`)
+//! or a snapshot (`sR`) response. The types here mirror the Darwin schema closely so
+//! that they can be deserialized directly with `quick-xml`'s serde support.
+
+use serde::Deserialize;
+
+mod alert;
+mod association;
+mod formation;
+mod schedule;
+mod station_message;
+mod train_order;
+mod train_status;
+
+pub use alert::{Alarm, AlarmAction, AlarmKind, AlarmSet, AlertService, AlertServices, TrainAlert};
+pub use association::{AssociatedService, Association, AssociationCategory};
+pub use formation::{
+    Coach, CoachLoading, Coaches, Formation, FormationLoading, ScheduleFormations, Toilet,
+    ToiletStatus, ToiletType,
+};
+pub use schedule::{CallingPoint, Deactivated, Schedule, ScheduleLocation};
+pub use station_message::{
+    MessageBody, MessageCategory, MessageInline, MessageLink, MessageSeverity, MessageStation,
+    StationMessage,
+};
+pub use train_order::{
+    TrainOrder, TrainOrderAction, TrainOrderItem, TrainOrderRid, TrainOrderService, TrainOrderSet,
+};
+pub use train_status::{ForecastTime, Platform, TrainStatus, TrainStatusLocation};
+
+/// The root element of every Darwin Push Port message.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Pport {
+    /// Local timestamp of the message, as sent by Darwin.
+    #[serde(rename = "@ts")]
+    pub ts: String,
+    /// Schema version the message conforms to.
+    #[serde(rename = "@version")]
+    pub version: String,
+    /// An update response, carrying live changes.
+    #[serde(rename = "uR")]
+    pub update: Option<DataResponse>,
+    /// A snapshot response, carrying the current state of the data.
+    #[serde(rename = "sR")]
+    pub snapshot: Option<DataResponse>,
+}
+
+impl Pport {
+    /// Deserializes a `Pport` document from an XML string.
+    pub fn from_xml(xml: &str) -> Result<Self, quick_xml::DeError> {
+        quick_xml::de::from_str(xml)
+    }
+
+    /// Returns the update or snapshot response carried by this message, if any.
+    pub fn response(&self) -> Option<&DataResponse> {
+        self.update.as_ref().or(self.snapshot.as_ref())
+    }
+}
+
+/// The body of an update (`uR`) or snapshot (`sR`) response.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct DataResponse {
+    /// The system that originated the update, e.g. `TD`, `Trust` or `CIS`.
+    #[serde(rename = "@updateOrigin")]
+    pub update_origin: Option<String>,
+    /// The source instance that requested the change, if any.
+    #[serde(rename = "@requestSource")]
+    pub request_source: Option<String>,
+    /// The identifier of the request that caused the change, if any.
+    #[serde(rename = "@requestID")]
+    pub request_id: Option<String>,
+    /// Train Status messages.
+    #[serde(rename = "TS", default)]
+    pub train_status: Vec<TrainStatus>,
+    /// Schedule messages.
+    #[serde(rename = "schedule", default)]
+    pub schedules: Vec<Schedule>,
+    /// Association messages.
+    #[serde(rename = "association", default)]
+    pub associations: Vec<Association>,
+    /// Deactivated schedule messages.
+    #[serde(rename = "deactivated", default)]
+    pub deactivated: Vec<Deactivated>,
+    /// Train order messages.
+    #[serde(rename = "trainOrder", default)]
+    pub train_orders: Vec<TrainOrder>,
+    /// Station messages.
+    #[serde(rename = "OW", default)]
+    pub station_messages: Vec<StationMessage>,
+    /// Schedule formation messages.
+    #[serde(rename = "scheduleFormations", default)]
+    pub schedule_formations: Vec<ScheduleFormations>,
+    /// Formation loading messages.
+    #[serde(rename = "formationLoading", default)]
+    pub formation_loading: Vec<FormationLoading>,
+    /// Train alert messages.
+    #[serde(rename = "trainAlert", default)]
+    pub train_alerts: Vec<TrainAlert>,
+    /// Alarm messages.
+    #[serde(rename = "alarm", default)]
+    pub alarms: Vec<Alarm>,
+}
+
+/// A coded reason for a delay or cancellation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DisruptionReason {
+    /// The reason code, looked up in the Darwin reference data.
+    #[serde(rename = "$text")]
+    pub code: u16,
+    /// TIPLOC of the location the reason applies at, if any.
+    #[serde(rename = "@tiploc")]
+    pub tiploc: Option<String>,
+    /// Whether the reason applies "near" rather than "at" the TIPLOC.
+    #[serde(rename = "@near", default)]
+    pub near: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
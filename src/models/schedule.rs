@@ -0,0 +1,218 @@
+use serde::{Deserialize, Deserializer};
+
+use super::{default_true, DisruptionReason};
+
+/// A `schedule` message, describing the full journey of a service.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Schedule {
+    /// RTTI unique train identifier.
+    #[serde(rename = "@rid")]
+    pub rid: String,
+    /// Train UID.
+    #[serde(rename = "@uid")]
+    pub uid: String,
+    /// Train ID (headcode).
+    #[serde(rename = "@trainId")]
+    pub train_id: String,
+    /// Retail service ID, if any.
+    #[serde(rename = "@rsid")]
+    pub rsid: Option<String>,
+    /// Scheduled start date, in `YYYY-MM-DD` format.
+    #[serde(rename = "@ssd")]
+    pub ssd: String,
+    /// ATOC code of the operating company.
+    #[serde(rename = "@toc")]
+    pub toc: String,
+    /// Type of service, e.g. `P` for a permanent passenger train. Darwin defaults this to `P`.
+    #[serde(rename = "@status")]
+    pub status: Option<String>,
+    /// Category of service, e.g. `OO` for an ordinary passenger train. Darwin defaults this to `OO`.
+    #[serde(rename = "@trainCat")]
+    pub train_cat: Option<String>,
+    /// Whether the service is a passenger service.
+    #[serde(rename = "@isPassengerSvc", default = "default_true")]
+    pub is_passenger_svc: bool,
+    /// Whether the service is active.
+    #[serde(rename = "@isActive", default = "default_true")]
+    pub is_active: bool,
+    /// Whether the service has been deleted and should no longer be used.
+    #[serde(rename = "@deleted", default)]
+    pub deleted: bool,
+    /// Whether the service is a charter service.
+    #[serde(rename = "@isCharter", default)]
+    pub is_charter: bool,
+    /// Whether the service is a "Q" path, i.e. it only runs when required.
+    #[serde(rename = "@qtrain", default)]
+    pub qtrain: bool,
+    /// The calling points of the service, in journey order.
+    ///
+    /// Child elements that are neither calling points nor one of the fields below are skipped, so
+    /// additions to the schema do not stop the whole message from decoding.
+    #[serde(rename = "$value", default, deserialize_with = "calling_points")]
+    pub calling_points: Vec<CallingPoint>,
+    /// The reason the service has been cancelled, if one has been given.
+    #[serde(rename = "cancelReason")]
+    pub cancel_reason: Option<DisruptionReason>,
+    /// TIPLOC of the location the service has been diverted via, if it has been diverted.
+    #[serde(rename = "divertedVia")]
+    pub diverted_via: Option<String>,
+    /// The reason the service has been diverted, if one has been given.
+    #[serde(rename = "diversionReason")]
+    pub diversion_reason: Option<DisruptionReason>,
+}
+
+/// A child element of `schedule` without a named field in [`Schedule`].
+#[derive(Deserialize)]
+enum ScheduleChild {
+    #[serde(rename = "OR")]
+    Origin(ScheduleLocation),
+    #[serde(rename = "OPOR")]
+    OperationalOrigin(ScheduleLocation),
+    #[serde(rename = "IP")]
+    Intermediate(ScheduleLocation),
+    #[serde(rename = "OPIP")]
+    OperationalIntermediate(ScheduleLocation),
+    #[serde(rename = "PP")]
+    Passing(ScheduleLocation),
+    #[serde(rename = "DT")]
+    Destination(ScheduleLocation),
+    #[serde(rename = "OPDT")]
+    OperationalDestination(ScheduleLocation),
+    #[serde(other)]
+    Unknown,
+}
+
+/// Deserializes the children of `schedule`, keeping only the calling points.
+fn calling_points<'de, D>(deserializer: D) -> Result<Vec<CallingPoint>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let children = Vec::<ScheduleChild>::deserialize(deserializer)?;
+    Ok(children
+        .into_iter()
+        .filter_map(|child| match child {
+            ScheduleChild::Origin(location) => Some(CallingPoint::Origin(location)),
+            ScheduleChild::OperationalOrigin(location) => {
+                Some(CallingPoint::OperationalOrigin(location))
+            }
+            ScheduleChild::Intermediate(location) => Some(CallingPoint::Intermediate(location)),
+            ScheduleChild::OperationalIntermediate(location) => {
+                Some(CallingPoint::OperationalIntermediate(location))
+            }
+            ScheduleChild::Passing(location) => Some(CallingPoint::Passing(location)),
+            ScheduleChild::Destination(location) => Some(CallingPoint::Destination(location)),
+            ScheduleChild::OperationalDestination(location) => {
+                Some(CallingPoint::OperationalDestination(location))
+            }
+            ScheduleChild::Unknown => None,
+        })
+        .collect())
+}
+
+/// A single location in a schedule, tagged by the role it plays in the journey.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum CallingPoint {
+    /// The origin of the service.
+    #[serde(rename = "OR")]
+    Origin(ScheduleLocation),
+    /// An operational origin, not advertised to the public.
+    #[serde(rename = "OPOR")]
+    OperationalOrigin(ScheduleLocation),
+    /// An intermediate calling point.
+    #[serde(rename = "IP")]
+    Intermediate(ScheduleLocation),
+    /// An operational intermediate calling point, not advertised to the public.
+    #[serde(rename = "OPIP")]
+    OperationalIntermediate(ScheduleLocation),
+    /// A location the service passes without stopping.
+    #[serde(rename = "PP")]
+    Passing(ScheduleLocation),
+    /// The destination of the service.
+    #[serde(rename = "DT")]
+    Destination(ScheduleLocation),
+    /// An operational destination, not advertised to the public.
+    #[serde(rename = "OPDT")]
+    OperationalDestination(ScheduleLocation),
+}
+
+impl CallingPoint {
+    /// Returns the location details, whatever the role of the calling point.
+    pub fn location(&self) -> &ScheduleLocation {
+        match self {
+            CallingPoint::Origin(location)
+            | CallingPoint::OperationalOrigin(location)
+            | CallingPoint::Intermediate(location)
+            | CallingPoint::OperationalIntermediate(location)
+            | CallingPoint::Passing(location)
+            | CallingPoint::Destination(location)
+            | CallingPoint::OperationalDestination(location) => location,
+        }
+    }
+
+    /// Returns `true` for calling points that are advertised to the public.
+    pub fn is_public(&self) -> bool {
+        matches!(
+            self,
+            CallingPoint::Origin(_) | CallingPoint::Intermediate(_) | CallingPoint::Destination(_)
+        )
+    }
+}
+
+/// The times and activities at a single schedule location.
+///
+/// Which times are present depends on the type of calling point; passing points, for
+/// example, only ever carry a working time of passing.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScheduleLocation {
+    /// TIPLOC of the location.
+    #[serde(rename = "@tpl")]
+    pub tpl: String,
+    /// Current activity codes, as a string of two-character codes.
+    #[serde(rename = "@act")]
+    pub act: Option<String>,
+    /// Planned activity codes, if they differ from the current ones.
+    #[serde(rename = "@planAct")]
+    pub plan_act: Option<String>,
+    /// Whether the service is cancelled at this location.
+    #[serde(rename = "@can", default)]
+    pub can: bool,
+    /// Public scheduled time of arrival.
+    #[serde(rename = "@pta")]
+    pub pta: Option<String>,
+    /// Public scheduled time of departure.
+    #[serde(rename = "@ptd")]
+    pub ptd: Option<String>,
+    /// Working scheduled time of arrival.
+    #[serde(rename = "@wta")]
+    pub wta: Option<String>,
+    /// Working scheduled time of departure.
+    #[serde(rename = "@wtd")]
+    pub wtd: Option<String>,
+    /// Working scheduled time of passing.
+    #[serde(rename = "@wtp")]
+    pub wtp: Option<String>,
+    /// Delay, in minutes, implied by a change to the route of the service.
+    #[serde(rename = "@rdelay", default)]
+    pub rdelay: i32,
+}
+
+impl ScheduleLocation {
+    /// Splits the current activity codes into their two-character codes.
+    pub fn activities(&self) -> Vec<&str> {
+        let act = self.act.as_deref().unwrap_or_default();
+        (0..act.len())
+            .step_by(2)
+            .filter_map(|i| act.get(i..(i + 2).min(act.len())))
+            .map(str::trim)
+            .filter(|code| !code.is_empty())
+            .collect()
+    }
+}
+
+/// A `deactivated` message, indicating a schedule is no longer active and can be discarded.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Deactivated {
+    /// RTTI unique train identifier.
+    #[serde(rename = "@rid")]
+    pub rid: String,
+}
@@ -0,0 +1,154 @@
+use serde::Deserialize;
+
+/// A station message (`OW`), shown on customer information screens.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StationMessage {
+    /// Unique identifier of the message.
+    #[serde(rename = "@id")]
+    pub id: u32,
+    /// The category of the message.
+    #[serde(rename = "@cat")]
+    pub cat: MessageCategory,
+    /// The severity of the message.
+    #[serde(rename = "@sev")]
+    pub sev: MessageSeverity,
+    /// Whether the message should be suppressed from public display.
+    #[serde(rename = "@suppress", default)]
+    pub suppress: bool,
+    /// The stations the message applies to. A message with no stations has been withdrawn.
+    #[serde(rename = "Station", default)]
+    pub stations: Vec<MessageStation>,
+    /// The body of the message.
+    #[serde(rename = "Msg")]
+    pub msg: MessageBody,
+}
+
+impl StationMessage {
+    /// Returns the CRS codes of the stations the message applies to.
+    pub fn crs_codes(&self) -> Vec<&str> {
+        self.stations
+            .iter()
+            .map(|station| station.crs.as_str())
+            .collect()
+    }
+}
+
+/// The category of a station message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum MessageCategory {
+    /// Disruption to train services.
+    Train,
+    /// Facilities at the station, such as lifts or ticket offices.
+    Station,
+    /// Connecting services, such as buses or ferries.
+    Connections,
+    /// Problems with the information systems themselves.
+    System,
+    /// Anything not covered by another category.
+    Misc,
+    /// Advance notice of planned changes to train services.
+    PriorTrains,
+    /// Advance notice of other planned changes.
+    PriorOther,
+}
+
+/// The severity of a station message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub enum MessageSeverity {
+    /// Routine information.
+    #[serde(rename = "0")]
+    Normal,
+    /// Minor disruption.
+    #[serde(rename = "1")]
+    Minor,
+    /// Major disruption.
+    #[serde(rename = "2")]
+    Major,
+    /// Severe disruption.
+    #[serde(rename = "3")]
+    Severe,
+}
+
+/// A station a message applies to.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MessageStation {
+    /// CRS code of the station.
+    #[serde(rename = "@crs")]
+    pub crs: String,
+}
+
+/// The body of a station message, a mix of text, paragraphs and links.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct MessageBody {
+    /// The content of the body, in document order.
+    #[serde(rename = "$value", default)]
+    pub inlines: Vec<MessageInline>,
+}
+
+impl MessageBody {
+    /// Flattens the body into plain text.
+    ///
+    /// Link text is kept inline and each paragraph is placed on its own line.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.write_text(&mut text);
+        text.trim_end().to_string()
+    }
+
+    fn write_text(&self, text: &mut String) {
+        for inline in &self.inlines {
+            match inline {
+                MessageInline::Text(words) => push_words(text, words),
+                MessageInline::Link(link) => push_words(text, &link.text),
+                MessageInline::Paragraph(paragraph) => {
+                    if !text.is_empty() && !text.ends_with('\n') {
+                        text.push('\n');
+                    }
+                    paragraph.write_text(text);
+                    text.push('\n');
+                }
+            }
+        }
+    }
+}
+
+/// Appends a run of words, separating it from any preceding text with a space.
+///
+/// The XML deserializer trims whitespace around text nodes, so spacing is restored here,
+/// except before punctuation that would normally follow a word directly.
+fn push_words(text: &mut String, words: &str) {
+    let words = words.trim();
+    if words.is_empty() {
+        return;
+    }
+    let joins_previous = words.starts_with(['.', ',', ';', ':', '!', '?', ')']);
+    if !text.is_empty() && !text.ends_with(['\n', ' ', '(']) && !joins_previous {
+        text.push(' ');
+    }
+    text.push_str(words);
+}
+
+/// A single piece of content in a station message body.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum MessageInline {
+    /// Plain text.
+    #[serde(rename = "$text")]
+    Text(String),
+    /// A paragraph, which may itself contain text and links.
+    #[serde(rename = "p")]
+    Paragraph(MessageBody),
+    /// A hyperlink.
+    #[serde(rename = "a")]
+    Link(MessageLink),
+}
+
+/// A hyperlink in a station message body.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MessageLink {
+    /// The target of the link.
+    #[serde(rename = "@href")]
+    pub href: String,
+    /// The text of the link.
+    #[serde(rename = "$text", default)]
+    pub text: String,
+}
@@ -0,0 +1,79 @@
+use serde::Deserialize;
+
+/// A `trainOrder` message, giving the order services are expected to arrive at a platform.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TrainOrder {
+    /// TIPLOC of the location the order applies at.
+    #[serde(rename = "@tiploc")]
+    pub tiploc: String,
+    /// CRS code of the station the order applies at.
+    #[serde(rename = "@crs")]
+    pub crs: String,
+    /// The platform the order applies to.
+    #[serde(rename = "@platform")]
+    pub platform: String,
+    /// Whether the order is being set or cleared.
+    #[serde(rename = "$value")]
+    pub action: TrainOrderAction,
+}
+
+/// The change a train order message makes to the platform.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum TrainOrderAction {
+    /// Sets the order of the next services at the platform.
+    #[serde(rename = "set")]
+    Set(Box<TrainOrderSet>),
+    /// Clears any previously set order for the platform.
+    #[serde(rename = "clear")]
+    Clear,
+}
+
+/// The first, second and third services expected at a platform.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TrainOrderSet {
+    /// The first service.
+    pub first: TrainOrderItem,
+    /// The second service, if any.
+    pub second: Option<TrainOrderItem>,
+    /// The third service, if any.
+    pub third: Option<TrainOrderItem>,
+}
+
+/// A single service in a train order.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TrainOrderItem {
+    /// The identifier of the service.
+    #[serde(rename = "$value")]
+    pub service: TrainOrderService,
+}
+
+/// How a service in a train order is identified.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum TrainOrderService {
+    /// A service known to Darwin, identified by its RID.
+    #[serde(rename = "rid")]
+    Rid(TrainOrderRid),
+    /// A service not known to Darwin, identified only by its train ID (headcode).
+    #[serde(rename = "trainID")]
+    TrainId(String),
+}
+
+/// The RID of a service in a train order, along with its times at the location.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TrainOrderRid {
+    /// RTTI unique train identifier.
+    #[serde(rename = "$text")]
+    pub rid: String,
+    /// Working scheduled time of arrival.
+    #[serde(rename = "@wta")]
+    pub wta: Option<String>,
+    /// Working scheduled time of departure.
+    #[serde(rename = "@wtd")]
+    pub wtd: Option<String>,
+    /// Public scheduled time of arrival.
+    #[serde(rename = "@pta")]
+    pub pta: Option<String>,
+    /// Public scheduled time of departure.
+    #[serde(rename = "@ptd")]
+    pub ptd: Option<String>,
+}
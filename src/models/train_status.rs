@@ -0,0 +1,98 @@
+use serde::Deserialize;
+
+use super::DisruptionReason;
+
+/// A Train Status (`TS`) message, carrying forecast and actual times for a service.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TrainStatus {
+    /// RTTI unique train identifier.
+    #[serde(rename = "@rid")]
+    pub rid: String,
+    /// Train UID.
+    #[serde(rename = "@uid")]
+    pub uid: String,
+    /// Scheduled start date, in `YYYY-MM-DD` format.
+    #[serde(rename = "@ssd")]
+    pub ssd: String,
+    /// The reason for the service running late, if one has been given.
+    #[serde(rename = "LateReason")]
+    pub late_reason: Option<DisruptionReason>,
+    /// Updated forecasts for the locations the service calls at or passes.
+    #[serde(rename = "Location", default)]
+    pub locations: Vec<TrainStatusLocation>,
+}
+
+/// Forecast data for a single location in a Train Status message.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TrainStatusLocation {
+    /// TIPLOC of the location.
+    #[serde(rename = "@tpl")]
+    pub tpl: String,
+    /// Working scheduled time of arrival.
+    #[serde(rename = "@wta")]
+    pub wta: Option<String>,
+    /// Working scheduled time of departure.
+    #[serde(rename = "@wtd")]
+    pub wtd: Option<String>,
+    /// Working scheduled time of passing.
+    #[serde(rename = "@wtp")]
+    pub wtp: Option<String>,
+    /// Public scheduled time of arrival.
+    #[serde(rename = "@pta")]
+    pub pta: Option<String>,
+    /// Public scheduled time of departure.
+    #[serde(rename = "@ptd")]
+    pub ptd: Option<String>,
+    /// Forecast data for the arrival at this location.
+    pub arr: Option<ForecastTime>,
+    /// Forecast data for the departure from this location.
+    pub dep: Option<ForecastTime>,
+    /// Forecast data for the pass of this location.
+    pub pass: Option<ForecastTime>,
+    /// Current platform number.
+    pub plat: Option<Platform>,
+    /// Whether the service is suppressed at this location.
+    pub suppr: Option<bool>,
+    /// The length of the service at this location, in coaches.
+    pub length: Option<u16>,
+}
+
+/// Forecast and actual time data for an arrival, departure or pass.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct ForecastTime {
+    /// Estimated time, based on the public schedule.
+    #[serde(rename = "@et")]
+    pub et: Option<String>,
+    /// Estimated time, based on the working schedule.
+    #[serde(rename = "@wet")]
+    pub wet: Option<String>,
+    /// Actual time.
+    #[serde(rename = "@at")]
+    pub at: Option<String>,
+    /// Whether a previously reported actual time has been removed.
+    #[serde(rename = "@atRemoved", default)]
+    pub at_removed: bool,
+    /// Whether the forecast is "delayed", i.e. unknown but late.
+    #[serde(rename = "@delayed", default)]
+    pub delayed: bool,
+    /// The source of the forecast or actual time, e.g. `Darwin` or `TRUST`.
+    #[serde(rename = "@src")]
+    pub src: Option<String>,
+}
+
+/// A platform number, along with how it may be displayed.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Platform {
+    /// The platform number.
+    #[serde(rename = "$text")]
+    pub number: String,
+    /// Whether the platform should be suppressed from public display.
+    #[serde(rename = "@platsup", default)]
+    pub platsup: bool,
+    /// Whether CIS has suppressed the platform.
+    #[serde(rename = "@cisPlatsup", default)]
+    pub cis_platsup: bool,
+    /// Whether the platform has been confirmed, e.g. by a track circuit.
+    #[serde(rename = "@conf", default)]
+    pub conf: bool,
+}
@@ -0,0 +1,484 @@
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::error::PushPortError;
+#[cfg(feature = "tls")]
+use crate::tls::TlsOptions;
+
+/// Settings used by [`NationalRailPushPortClient::connect_with`](crate::NationalRailPushPortClient::connect_with).
+#[derive(Clone)]
+pub struct ConnectOptions {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) username: String,
+    pub(crate) password: String,
+    pub(crate) virtual_host: Option<String>,
+    pub(crate) accept_version: String,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) tcp_keepalive: Option<Duration>,
+    pub(crate) read_buffer_size: usize,
+    pub(crate) message_capacity: usize,
+    pub(crate) socket_buffer_sizes: Option<(usize, usize)>,
+    pub(crate) heart_beat: (Duration, Duration),
+    pub(crate) heartbeat_grace: f64,
+    pub(crate) receipt_timeout: Duration,
+    pub(crate) client_id: Option<String>,
+    pub(crate) headers: Vec<(String, String)>,
+    #[cfg(feature = "tls")]
+    pub(crate) tls: Option<TlsOptions>,
+}
+
+impl ConnectOptions {
+    /// Creates options for the given server and credentials, with default heart-beat settings.
+    pub fn new(
+        host: impl Into<String>,
+        port: u16,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            username: username.into(),
+            password: password.into(),
+            virtual_host: None,
+            accept_version: "1.2".to_string(),
+            connect_timeout: Some(Duration::from_secs(30)),
+            tcp_keepalive: None,
+            read_buffer_size: 8192,
+            message_capacity: 1024,
+            socket_buffer_sizes: None,
+            heart_beat: (Duration::from_secs(10), Duration::from_secs(10)),
+            heartbeat_grace: 2.0,
+            receipt_timeout: Duration::from_secs(10),
+            client_id: None,
+            headers: Vec::new(),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Loads options from the `DARWIN_HOST`, `DARWIN_PORT`, `DARWIN_USER` and `DARWIN_PASSWORD`
+    /// environment variables, along with these optional ones:
+    ///
+    /// - `DARWIN_VIRTUAL_HOST`, `DARWIN_CLIENT_ID` and `DARWIN_ACCEPT_VERSION`
+    /// - `DARWIN_HEART_BEAT_MS`, the send and receive intervals separated by a comma, e.g.
+    ///   `15000,15000`, and `DARWIN_HEARTBEAT_GRACE`, a finite number of at least 1
+    /// - `DARWIN_CONNECT_TIMEOUT_MS`, where 0 waits indefinitely, and `DARWIN_RECEIPT_TIMEOUT_MS`
+    ///
+    /// `DARWIN_PORT` defaults to 61613, and anything not set keeps its default. Socket and buffer
+    /// settings, extra CONNECT headers and TLS are not read from the environment. Fails with
+    /// [`PushPortError::Config`] if a required variable is missing or a value cannot be parsed.
+    pub fn from_env() -> Result<Self, PushPortError> {
+        Self::from_env_prefixed("DARWIN")
+    }
+
+    /// Loads options like [`from_env`](Self::from_env), from variables starting with `prefix`
+    /// instead of `DARWIN`, e.g. `REPLAY_HOST` for the prefix `REPLAY`.
+    pub fn from_env_prefixed(prefix: &str) -> Result<Self, PushPortError> {
+        let var = |name: &str| env::var(format!("{}_{}", prefix, name)).ok();
+        let required = |name: &str| {
+            var(name)
+                .ok_or_else(|| PushPortError::Config(format!("{}_{} is not set", prefix, name)))
+        };
+        let millis = |name: &str| -> Result<Option<Duration>, PushPortError> {
+            Ok(parse_var::<u64>(prefix, name)?.map(Duration::from_millis))
+        };
+
+        let mut options = Self::new(
+            required("HOST")?,
+            parse_var(prefix, "PORT")?.unwrap_or(61613),
+            required("USER")?,
+            required("PASSWORD")?,
+        );
+        if let Some(virtual_host) = var("VIRTUAL_HOST") {
+            options = options.virtual_host(virtual_host);
+        }
+        if let Some(client_id) = var("CLIENT_ID") {
+            options = options.client_id(client_id);
+        }
+        if let Some(versions) = var("ACCEPT_VERSION") {
+            options = options.accept_version(versions);
+        }
+        if let Some(heart_beat) = var("HEART_BEAT_MS") {
+            let (send, receive) = heart_beat
+                .split_once(',')
+                .and_then(|(send, receive)| {
+                    Some((send.trim().parse().ok()?, receive.trim().parse().ok()?))
+                })
+                .ok_or_else(|| {
+                    PushPortError::Config(format!(
+                        "{}_HEART_BEAT_MS is not two comma-separated numbers: {}",
+                        prefix, heart_beat
+                    ))
+                })?;
+            options =
+                options.heart_beat(Duration::from_millis(send), Duration::from_millis(receive));
+        }
+        if let Some(grace) = parse_var::<f64>(prefix, "HEARTBEAT_GRACE")? {
+            if !grace.is_finite() || grace < 1.0 {
+                return Err(PushPortError::Config(format!(
+                    "{}_HEARTBEAT_GRACE is not a finite number of at least 1: {}",
+                    prefix, grace
+                )));
+            }
+            options = options.heartbeat_grace(grace);
+        }
+        if let Some(limit) = millis("CONNECT_TIMEOUT_MS")? {
+            options = options.connect_timeout((!limit.is_zero()).then_some(limit));
+        }
+        if let Some(limit) = millis("RECEIPT_TIMEOUT_MS")? {
+            options = options.receipt_timeout(limit);
+        }
+        Ok(options)
+    }
+
+    /// Sets the `host` header of the CONNECT frame, naming the virtual host on the broker, when it
+    /// differs from the host connected to. Defaults to the host connected to.
+    pub fn virtual_host(mut self, virtual_host: impl Into<String>) -> Self {
+        self.virtual_host = Some(virtual_host.into());
+        self
+    }
+
+    /// Sets the `accept-version` header, the comma-separated STOMP versions the client will accept,
+    /// e.g. `"1.1,1.2"`. Defaults to `"1.2"`. The version the server chose is available from
+    /// [`ConnectionInfo::version`](crate::ConnectionInfo::version).
+    pub fn accept_version(mut self, versions: impl Into<String>) -> Self {
+        self.accept_version = versions.into();
+        self
+    }
+
+    /// Sets how long connecting may take, including any TLS handshake and waiting for the server's
+    /// CONNECTED frame. Defaults to 30 seconds; `None` waits indefinitely.
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Enables TCP keepalive, probing the connection after it has been idle for `idle`.
+    ///
+    /// This complements STOMP heart-beats, e.g. for keeping NAT mappings alive when heart-beats are
+    /// disabled.
+    pub fn tcp_keepalive(mut self, idle: Duration) -> Self {
+        self.tcp_keepalive = Some(idle);
+        self
+    }
+
+    /// Sets how many bytes are read from the connection at a time. Defaults to 8 KiB.
+    pub fn read_buffer_size(mut self, bytes: usize) -> Self {
+        self.read_buffer_size = bytes.max(1);
+        self
+    }
+
+    /// Sets how many received messages are buffered for each [`Subscription`](crate::Subscription)
+    /// handle, and for the client's own message stream. Defaults to 1024.
+    ///
+    /// When a buffer is full, the client stops reading from the connection until the messages are
+    /// consumed, so a slow consumer slows the server down rather than using ever more memory.
+    /// Receipts and server errors arrive on the same connection, so keep live handles polled or
+    /// drop them. The client's own stream only holds the connection up once something has started
+    /// reading it; before that, messages that do not fit are dropped.
+    pub fn message_capacity(mut self, messages: usize) -> Self {
+        self.message_capacity = messages.max(1);
+        self
+    }
+
+    /// Sets the operating system's receive and send buffer sizes for the socket, e.g. to absorb
+    /// bursts of large snapshot messages.
+    pub fn socket_buffer_sizes(mut self, receive: usize, send: usize) -> Self {
+        self.socket_buffer_sizes = Some((receive, send));
+        self
+    }
+
+    /// Sets the heart-beat intervals to offer the server.
+    ///
+    /// `send` is how often the client can send heart-beats and `receive` is how often it would like to
+    /// receive them. A zero duration disables heart-beats in that direction.
+    pub fn heart_beat(mut self, send: Duration, receive: Duration) -> Self {
+        self.heart_beat = (send, receive);
+        self
+    }
+
+    /// Sets how many negotiated server heart-beat intervals may pass without any data before the
+    /// connection is considered dead. Defaults to 2. Values below 1 are treated as 1.
+    pub fn heartbeat_grace(mut self, multiplier: f64) -> Self {
+        self.heartbeat_grace = multiplier;
+        self
+    }
+
+    /// Sets how long to wait for the server to confirm a frame sent with a receipt, such as
+    /// DISCONNECT. Defaults to 10 seconds.
+    pub fn receipt_timeout(mut self, timeout: Duration) -> Self {
+        self.receipt_timeout = timeout;
+        self
+    }
+
+    /// Sets the `client-id` header, which ActiveMQ uses to identify the owner of durable
+    /// subscriptions across reconnects.
+    pub fn client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// Adds an extra header to the CONNECT frame.
+    ///
+    /// Extra headers are sent after the ones the client sets itself, and STOMP uses the first
+    /// occurrence of a header, so they cannot override them.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Connects over TLS, usually on port 61614, so credentials are not sent in clear text.
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, tls: TlsOptions) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+}
+
+/// Parses the variable `{prefix}_{name}`, if it is set.
+fn parse_var<T: FromStr>(prefix: &str, name: &str) -> Result<Option<T>, PushPortError> {
+    match env::var(format!("{}_{}", prefix, name)) {
+        Ok(value) => value.trim().parse().map(Some).map_err(|_| {
+            PushPortError::Config(format!("{}_{} is not valid: {}", prefix, name, value))
+        }),
+        Err(_) => Ok(None),
+    }
+}
+
+impl fmt::Debug for ConnectOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ConnectOptions");
+        debug
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("virtual_host", &self.virtual_host)
+            .field("accept_version", &self.accept_version)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("tcp_keepalive", &self.tcp_keepalive)
+            .field("read_buffer_size", &self.read_buffer_size)
+            .field("message_capacity", &self.message_capacity)
+            .field("socket_buffer_sizes", &self.socket_buffer_sizes)
+            .field("heart_beat", &self.heart_beat)
+            .field("heartbeat_grace", &self.heartbeat_grace)
+            .field("receipt_timeout", &self.receipt_timeout)
+            .field("client_id", &self.client_id)
+            .field("headers", &self.headers);
+        #[cfg(feature = "tls")]
+        debug.field("tls", &self.tls);
+        debug.finish()
+    }
+}
+
+/// How messages delivered for a subscription are acknowledged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AckMode {
+    /// The server considers a message acknowledged as soon as it is sent.
+    #[default]
+    Auto,
+    /// Messages must be acknowledged with [`Message::ack`](crate::Message::ack), which also
+    /// acknowledges every earlier message on the subscription.
+    Client,
+    /// Messages must be acknowledged one by one with [`Message::ack`](crate::Message::ack).
+    ClientIndividual,
+}
+
+impl AckMode {
+    /// The value of the `ack` header for this mode.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AckMode::Auto => "auto",
+            AckMode::Client => "client",
+            AckMode::ClientIndividual => "client-individual",
+        }
+    }
+}
+
+/// Settings used by [`NationalRailPushPortClient::subscribe_with`](crate::NationalRailPushPortClient::subscribe_with).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscribeOptions {
+    pub(crate) ack: AckMode,
+    pub(crate) durable_name: Option<String>,
+    pub(crate) headers: Vec<(String, String)>,
+}
+
+impl SubscribeOptions {
+    /// Creates options with automatic acknowledgement.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how messages are acknowledged.
+    pub fn ack(mut self, ack: AckMode) -> Self {
+        self.ack = ack;
+        self
+    }
+
+    /// Makes the subscription durable under the given name, using ActiveMQ's
+    /// `activemq.subscriptionName` header.
+    ///
+    /// The broker keeps messages for a durable topic subscription while the client is away. The
+    /// connection must also set a [`client_id`](ConnectOptions::client_id), and both must stay the
+    /// same across reconnects.
+    pub fn durable(mut self, subscription_name: impl Into<String>) -> Self {
+        self.durable_name = Some(subscription_name.into());
+        self
+    }
+
+    /// Adds an extra header to the SUBSCRIBE frame, e.g. `selector` or `activemq.prefetchSize`.
+    ///
+    /// As with [`ConnectOptions::header`], extra headers cannot override the ones the client sets.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// Settings used by [`NationalRailPushPortClient::send_with`](crate::NationalRailPushPortClient::send_with).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SendOptions {
+    pub(crate) receipt: bool,
+    pub(crate) gzip: bool,
+    pub(crate) headers: Vec<(String, String)>,
+}
+
+impl SendOptions {
+    /// Creates options that send the body as given, without waiting for a receipt.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the server to confirm the SEND with a RECEIPT, and waits for it up to the configured
+    /// [`receipt_timeout`](ConnectOptions::receipt_timeout).
+    pub fn receipt(mut self) -> Self {
+        self.receipt = true;
+        self
+    }
+
+    /// Gzips the body before sending, the way Darwin compresses its own messages.
+    pub fn gzip(mut self) -> Self {
+        self.gzip = true;
+        self
+    }
+
+    /// Adds an extra header to the SEND frame, e.g. `persistent` or `amq-msg-type`.
+    ///
+    /// Extra headers cannot override `destination`, `receipt` or `content-length`, which the client
+    /// sets itself.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(prefix: &str, vars: &[(&str, &str)]) {
+        for (name, value) in vars {
+            env::set_var(format!("{}_{}", prefix, name), value);
+        }
+    }
+
+    #[test]
+    fn loads_required_settings_with_defaults() {
+        set(
+            "TEST_REQUIRED",
+            &[
+                ("HOST", "darwin.example"),
+                ("USER", "consumer"),
+                ("PASSWORD", "secret"),
+            ],
+        );
+        let options = ConnectOptions::from_env_prefixed("TEST_REQUIRED").unwrap();
+        let defaults = ConnectOptions::new("darwin.example", 61613, "consumer", "secret");
+
+        assert_eq!(options.host, "darwin.example");
+        assert_eq!(options.port, 61613);
+        assert_eq!(options.username, "consumer");
+        assert_eq!(options.password, "secret");
+        assert_eq!(options.virtual_host, None);
+        assert_eq!(options.client_id, None);
+        assert_eq!(options.heart_beat, defaults.heart_beat);
+        assert_eq!(options.connect_timeout, defaults.connect_timeout);
+        assert_eq!(options.receipt_timeout, defaults.receipt_timeout);
+        assert!(!format!("{:?}", options).contains("secret"));
+    }
+
+    #[test]
+    fn loads_optional_settings() {
+        set(
+            "TEST_OPTIONAL",
+            &[
+                ("HOST", "darwin.example"),
+                ("PORT", "61614"),
+                ("USER", "consumer"),
+                ("PASSWORD", "secret"),
+                ("VIRTUAL_HOST", "darwin-dist"),
+                ("CLIENT_ID", "consumer-1"),
+                ("ACCEPT_VERSION", "1.1,1.2"),
+                ("HEART_BEAT_MS", "15000, 20000"),
+                ("HEARTBEAT_GRACE", "3"),
+                ("CONNECT_TIMEOUT_MS", "0"),
+                ("RECEIPT_TIMEOUT_MS", "2500"),
+            ],
+        );
+        let options = ConnectOptions::from_env_prefixed("TEST_OPTIONAL").unwrap();
+
+        assert_eq!(options.port, 61614);
+        assert_eq!(options.virtual_host.as_deref(), Some("darwin-dist"));
+        assert_eq!(options.client_id.as_deref(), Some("consumer-1"));
+        assert_eq!(options.accept_version, "1.1,1.2");
+        assert_eq!(
+            options.heart_beat,
+            (Duration::from_secs(15), Duration::from_secs(20))
+        );
+        assert_eq!(options.heartbeat_grace, 3.0);
+        assert_eq!(options.connect_timeout, None);
+        assert_eq!(options.receipt_timeout, Duration::from_millis(2500));
+    }
+
+    #[test]
+    fn rejects_missing_and_invalid_settings() {
+        assert!(matches!(
+            ConnectOptions::from_env_prefixed("TEST_MISSING"),
+            Err(PushPortError::Config(_))
+        ));
+
+        let required = [
+            ("HOST", "darwin.example"),
+            ("USER", "consumer"),
+            ("PASSWORD", "secret"),
+        ];
+        set("TEST_BAD_PORT", &required);
+        set("TEST_BAD_PORT", &[("PORT", "not-a-port")]);
+        assert!(matches!(
+            ConnectOptions::from_env_prefixed("TEST_BAD_PORT"),
+            Err(PushPortError::Config(_))
+        ));
+        set("TEST_BAD_HEART_BEAT", &required);
+        set("TEST_BAD_HEART_BEAT", &[("HEART_BEAT_MS", "15000")]);
+        assert!(matches!(
+            ConnectOptions::from_env_prefixed("TEST_BAD_HEART_BEAT"),
+            Err(PushPortError::Config(_))
+        ));
+        for (prefix, grace) in [
+            ("TEST_INFINITE_GRACE", "inf"),
+            ("TEST_NAN_GRACE", "NaN"),
+            ("TEST_SMALL_GRACE", "0.5"),
+        ] {
+            set(prefix, &required);
+            set(prefix, &[("HEARTBEAT_GRACE", grace)]);
+            assert!(matches!(
+                ConnectOptions::from_env_prefixed(prefix),
+                Err(PushPortError::Config(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+use crate::error::PushPortError;
+use crate::frame::{parse_stomp_frame, skip_eols, StompFrame};
+use crate::heartbeat::Outbox;
+use crate::message::Message;
+use crate::receipt::Receipts;
+use crate::subscription::{Acknowledged, MessageSender, Routes};
+
+/// Reads frames from the connection and routes each message to the subscription it belongs to.
+///
+/// Messages for subscriptions without a live handle go to `inbox`, RECEIPT frames resolve the
+/// matching pending receipt, and ERROR frames are reported as [`PushPortError::ServerError`]. Runs
+/// until the connection closes, fails, the writer task reports a failed write on `writer_failed`,
+/// or the client owning `inbox` is dropped. Any error is passed on to every subscription and the
+/// inbox, and `writer_guard` is dropped on exit to stop the writer task.
+pub(crate) async fn run_reader<R>(
+    mut reader: R,
+    mut accumulated: Vec<u8>,
+    receive_timeout: Option<Duration>,
+    read_buffer_size: usize,
+    connection: Connection,
+    writer_guard: oneshot::Sender<()>,
+    writer_failed: oneshot::Receiver<std::io::Error>,
+) where
+    R: AsyncRead + Unpin,
+{
+    let result = tokio::select! {
+        result = read_frames(
+            &mut reader,
+            &mut accumulated,
+            receive_timeout,
+            read_buffer_size,
+            &connection,
+        ) => result,
+        Ok(e) = writer_failed => Err(PushPortError::Io(e)),
+        _ = connection.inbox.closed() => Ok(()),
+    };
+    drop(writer_guard);
+    connection.receipts.clear();
+
+    // Dropping the senders ends every subscription stream. A handle with a full buffer misses the
+    // error, but still sees its stream end.
+    let routes: Vec<_> = connection.routes.lock().unwrap().drain().collect();
+    if let Err(e) = result {
+        for (_, sender) in routes {
+            if let Some(sender) = sender {
+                let _ = sender.try_send(Err(e.clone_lossy()));
+            }
+        }
+        send_to_inbox(&connection, Err(e)).await;
+    }
+}
+
+/// Where the reader delivers the frames it has parsed.
+pub(crate) struct Connection {
+    /// Live subscription handles.
+    pub routes: Routes,
+    /// Subscriptions whose messages are given `outbox`, so they can acknowledge themselves.
+    pub acknowledged: Acknowledged,
+    /// Frames waiting for a RECEIPT.
+    pub receipts: Receipts,
+    /// Messages not claimed by a subscription handle.
+    pub inbox: MessageSender,
+    /// Set once the client starts reading `inbox`.
+    pub inbox_read: Arc<AtomicBool>,
+    /// The writer's queue.
+    pub outbox: Outbox,
+}
+
+async fn read_frames<R>(
+    reader: &mut R,
+    accumulated: &mut Vec<u8>,
+    receive_timeout: Option<Duration>,
+    read_buffer_size: usize,
+    connection: &Connection,
+) -> Result<(), PushPortError>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        loop {
+            // Heart-beat EOLs between frames are skipped by the parser.
+            let Some((frame_len, frame)) = parse_stomp_frame(accumulated)? else {
+                // Don't let heart-beats pile up while waiting for the rest of a frame.
+                accumulated.drain(..skip_eols(accumulated));
+                break;
+            };
+            // Remove the processed frame from the accumulator.
+            accumulated.drain(..frame_len);
+            dispatch(frame, connection).await;
+        }
+
+        let mut buf = vec![0u8; read_buffer_size];
+        let n = match receive_timeout {
+            Some(limit) => timeout(limit, reader.read(&mut buf))
+                .await
+                .map_err(|_| PushPortError::HeartbeatTimeout(limit))??,
+            None => reader.read(&mut buf).await?,
+        };
+        if n == 0 {
+            return Ok(());
+        }
+        accumulated.extend_from_slice(&buf[..n]);
+    }
+}
+
+/// Handles a frame according to its command.
+async fn dispatch(frame: StompFrame, connection: &Connection) {
+    match frame.command() {
+        "MESSAGE" => deliver(frame, connection).await,
+        "RECEIPT" => {
+            if let Some(receipt_id) = frame.header("receipt-id") {
+                connection.receipts.resolve(receipt_id);
+            }
+        }
+        "ERROR" => report(frame, connection).await,
+        // Nothing else the server sends needs handling.
+        _ => {}
+    }
+}
+
+/// Fails the receipt an ERROR frame answers, or passes the error on to every subscription and the
+/// inbox if it does not answer one.
+async fn report(frame: StompFrame, connection: &Connection) {
+    let receipt_id = frame.header("receipt-id").map(str::to_string);
+    let mut error = PushPortError::ServerError {
+        message: frame.header("message").map(str::to_string),
+        body: String::from_utf8_lossy(frame.body()).to_string(),
+        headers: frame.headers,
+    };
+    if let Some(receipt_id) = receipt_id {
+        match connection.receipts.fail(&receipt_id, error) {
+            Ok(()) => return,
+            Err(returned) => error = returned,
+        }
+    }
+
+    for sender in connection.routes.lock().unwrap().values().flatten() {
+        let _ = sender.try_send(Err(error.clone_lossy()));
+    }
+    send_to_inbox(connection, Err(error)).await;
+}
+
+/// Delivers a MESSAGE frame to the handle for its subscription, falling back to the inbox.
+///
+/// Waits while a handle's buffer is full, so that reading stops until the message is consumed.
+/// Messages for subscriptions that have been unsubscribed are dropped.
+async fn deliver(frame: StompFrame, connection: &Connection) {
+    let subscription = frame.header("subscription").map(str::to_string);
+    let acknowledged = subscription
+        .as_ref()
+        .is_some_and(|id| connection.acknowledged.lock().unwrap().contains(id));
+    let mut message = Message::from_frame(frame).map(|message| {
+        if acknowledged {
+            message.with_outbox(connection.outbox.clone())
+        } else {
+            message
+        }
+    });
+
+    if let Some(id) = subscription {
+        let route = connection.routes.lock().unwrap().get(&id).cloned();
+        match route {
+            Some(Some(sender)) => match sender.send(message).await {
+                Ok(()) => return,
+                Err(returned) => {
+                    let mut routes = connection.routes.lock().unwrap();
+                    if let Some(None) = routes.get(&id) {
+                        // Unsubscribed while waiting for space.
+                        return;
+                    }
+                    // The handle was dropped, so stop routing to it.
+                    routes.remove(&id);
+                    message = returned.0;
+                }
+            },
+            Some(None) => return,
+            None => {}
+        }
+    }
+    send_to_inbox(connection, message).await;
+}
+
+/// Passes a message or error on to the client's own stream.
+///
+/// Once the client has started reading the stream, this waits while it is full, like a handle's
+/// buffer. Until then nothing may ever read it, so anything that does not fit is dropped rather
+/// than holding up every subscription and heart-beat.
+async fn send_to_inbox(connection: &Connection, item: Result<Message, PushPortError>) {
+    if connection.inbox_read.load(Ordering::Relaxed) {
+        let _ = connection.inbox.send(item).await;
+    } else {
+        let _ = connection.inbox.try_send(item);
+    }
+}
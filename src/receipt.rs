@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+use crate::error::PushPortError;
+
+/// The outcome of a frame sent with a `receipt` header: a RECEIPT, or the ERROR the server sent
+/// instead.
+type ReceiptResult = Result<(), PushPortError>;
+
+/// Frames sent with a `receipt` header that are still waiting for the server's RECEIPT frame.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Receipts(Arc<Mutex<HashMap<String, oneshot::Sender<ReceiptResult>>>>);
+
+impl Receipts {
+    /// Starts waiting for the receipt with the given id.
+    pub fn register(&self, receipt_id: &str) -> PendingReceipt {
+        let (sender, receiver) = oneshot::channel();
+        self.0
+            .lock()
+            .unwrap()
+            .insert(receipt_id.to_string(), sender);
+        PendingReceipt {
+            receipt_id: receipt_id.to_string(),
+            receiver,
+        }
+    }
+
+    /// Stops waiting for the receipt with the given id, e.g. because its frame was never sent.
+    pub fn cancel(&self, receipt_id: &str) {
+        self.0.lock().unwrap().remove(receipt_id);
+    }
+
+    /// Wakes whoever is waiting for the receipt with the given id.
+    pub fn resolve(&self, receipt_id: &str) {
+        if let Some(sender) = self.0.lock().unwrap().remove(receipt_id) {
+            let _ = sender.send(Ok(()));
+        }
+    }
+
+    /// Fails the receipt with the given id, because the server answered its frame with an ERROR.
+    ///
+    /// Returns the error back if nobody is waiting for the receipt.
+    pub fn fail(&self, receipt_id: &str, error: PushPortError) -> Result<(), PushPortError> {
+        match self.0.lock().unwrap().remove(receipt_id) {
+            Some(sender) => {
+                let _ = sender.send(Err(error));
+                Ok(())
+            }
+            None => Err(error),
+        }
+    }
+
+    /// Fails every pending receipt, because the connection has closed.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// A receipt that has been requested but not yet received.
+#[derive(Debug)]
+pub(crate) struct PendingReceipt {
+    receipt_id: String,
+    receiver: oneshot::Receiver<ReceiptResult>,
+}
+
+impl PendingReceipt {
+    /// Waits up to `limit` for the server to confirm the frame.
+    pub async fn wait(self, limit: Duration) -> Result<(), PushPortError> {
+        match timeout(limit, self.receiver).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(PushPortError::ConnectionClosed),
+            Err(_) => Err(PushPortError::ReceiptTimeout {
+                receipt_id: self.receipt_id,
+            }),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use futures::stream::{self, Stream};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::client::NationalRailPushPortClient;
+use crate::error::PushPortError;
+use crate::message::Message;
+use crate::options::{ConnectOptions, SubscribeOptions};
+
+/// Lifecycle events emitted by a [`ReconnectingClient`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// The connection was lost.
+    Disconnected,
+    /// A reconnect attempt is about to be made. Attempts are numbered from 1.
+    Reconnecting(u32),
+    /// The connection was re-established and all subscriptions were restored.
+    Reconnected,
+}
+
+/// Jittered exponential backoff between reconnect attempts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backoff {
+    /// The delay before the first attempt.
+    pub initial: Duration,
+    /// The longest delay between attempts.
+    pub max: Duration,
+    /// The factor the delay grows by after each failed attempt.
+    pub multiplier: f64,
+    /// How many attempts to make before giving up, or `None` to keep trying forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            multiplier: 2.0,
+            max_attempts: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// Returns the delay before the given attempt (numbered from 1).
+    ///
+    /// The delay is picked at random from the upper half of the exponential delay, so that many
+    /// clients dropped at once do not all reconnect at the same moment.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = self.initial.as_secs_f64() * self.multiplier.powi(exponent);
+        let capped = base.min(self.max.as_secs_f64());
+        Duration::from_secs_f64(capped * (0.5 + 0.5 * random_fraction()))
+    }
+}
+
+/// Returns a random number in `[0, 1)`, seeded from the standard library's per-hasher random keys.
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// A client that transparently reconnects when the connection to the server is lost.
+///
+/// It keeps the connection options and every topic subscribed to, and after reconnecting it
+/// subscribes to them again. Progress is reported through [`events`](Self::events).
+pub struct ReconnectingClient {
+    options: ConnectOptions,
+    backoff: Backoff,
+    subscriptions: Vec<(String, SubscribeOptions)>,
+    client: Option<NationalRailPushPortClient>,
+    events: broadcast::Sender<ConnectionEvent>,
+}
+
+impl ReconnectingClient {
+    /// Connects to the server. The first attempt is made immediately and any error is returned,
+    /// so that bad configuration is reported rather than retried.
+    pub async fn connect(options: ConnectOptions, backoff: Backoff) -> Result<Self, PushPortError> {
+        let client = NationalRailPushPortClient::connect_with(options.clone()).await?;
+        let (events, _) = broadcast::channel(16);
+        Ok(Self {
+            options,
+            backoff,
+            subscriptions: Vec::new(),
+            client: Some(client),
+            events,
+        })
+    }
+
+    /// Returns a receiver for lifecycle events.
+    pub fn events(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns whether the client currently has an open connection.
+    ///
+    /// A lost connection is only noticed here; it is re-established by the next call to
+    /// [`next_message`](Self::next_message).
+    pub fn is_connected(&self) -> bool {
+        self.client
+            .as_ref()
+            .is_some_and(NationalRailPushPortClient::is_connected)
+    }
+
+    /// Subscribes to a destination, and again after every reconnect.
+    ///
+    /// Messages for the subscription are delivered through [`next_message`](Self::next_message) and
+    /// [`messages`](Self::messages).
+    pub async fn subscribe(&mut self, destination: &str) -> Result<(), PushPortError> {
+        self.subscribe_with(destination, SubscribeOptions::default())
+            .await
+    }
+
+    /// Subscribes to a destination using the given options, and again after every reconnect.
+    ///
+    /// The subscription is recorded before subscribing, so if the connection has been lost, the
+    /// error is returned but the subscription is still restored on the next reconnect.
+    pub async fn subscribe_with(
+        &mut self,
+        destination: &str,
+        options: SubscribeOptions,
+    ) -> Result<(), PushPortError> {
+        self.subscriptions
+            .push((destination.to_string(), options.clone()));
+        if let Some(client) = self.client.as_mut() {
+            client.subscribe_with(destination, options).await?;
+        }
+        Ok(())
+    }
+
+    /// Waits for the next message, reconnecting as many times as needed.
+    ///
+    /// Connection errors are only returned once the backoff gives up, or if the server rejects the
+    /// credentials. Errors decoding a single message are returned without reconnecting.
+    pub async fn next_message(&mut self) -> Result<Message, PushPortError> {
+        loop {
+            let result = match self.client.as_mut() {
+                Some(client) => client.next_message().await,
+                None => Ok(None),
+            };
+            match result {
+                Ok(Some(message)) => return Ok(message),
+                Ok(None) => {}
+                Err(e) if e.is_connection_lost() => {}
+                Err(e) => return Err(e),
+            }
+            self.reconnect().await?;
+        }
+    }
+
+    /// Returns a stream of messages that survives reconnects.
+    ///
+    /// The stream only ends after yielding an error that reconnecting could not recover from.
+    pub fn messages(&mut self) -> impl Stream<Item = Result<Message, PushPortError>> + '_ {
+        stream::unfold(Some(self), |client| async move {
+            let client = client?;
+            match client.next_message().await {
+                Ok(message) => Some((Ok(message), Some(client))),
+                Err(e) if e.is_connection_lost() || client.client.is_none() => Some((Err(e), None)),
+                Err(e) => Some((Err(e), Some(client))),
+            }
+        })
+    }
+
+    async fn reconnect(&mut self) -> Result<(), PushPortError> {
+        self.client = None;
+        self.emit(ConnectionEvent::Disconnected);
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            self.emit(ConnectionEvent::Reconnecting(attempt));
+            tokio::time::sleep(self.backoff.delay(attempt)).await;
+
+            match self.resume().await {
+                Ok(client) => {
+                    self.client = Some(client);
+                    self.emit(ConnectionEvent::Reconnected);
+                    return Ok(());
+                }
+                Err(e) if !e.is_connection_lost() => return Err(e),
+                Err(e) if self.backoff.max_attempts.is_some_and(|max| attempt >= max) => {
+                    return Err(e)
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// Opens a new connection and restores every subscription on it.
+    async fn resume(&self) -> Result<NationalRailPushPortClient, PushPortError> {
+        let mut client = NationalRailPushPortClient::connect_with(self.options.clone()).await?;
+        for (destination, options) in &self.subscriptions {
+            // Dropping the handle sends the subscription's messages to the client's own stream.
+            // Subscribing only fails if the new connection has already been lost, so try again.
+            client
+                .subscribe_with(destination, options.clone())
+                .await
+                .map_err(|_| PushPortError::ConnectionClosed)?;
+        }
+        Ok(client)
+    }
+
+    fn emit(&self, event: ConnectionEvent) {
+        // Nobody listening is not an error.
+        let _ = self.events.send(event);
+    }
+}
@@ -0,0 +1,102 @@
+use futures::Stream;
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+use crate::error::PushPortError;
+use crate::frame::FrameBuilder;
+use crate::heartbeat::Outbox;
+use crate::message::Message;
+
+/// The sending side of a channel of received messages.
+pub(crate) type MessageSender = mpsc::Sender<Result<Message, PushPortError>>;
+
+/// The receiving side of a channel of received messages.
+pub(crate) type MessageReceiver = mpsc::Receiver<Result<Message, PushPortError>>;
+
+/// Live subscription handles, keyed by subscription id.
+///
+/// `None` marks a subscription that has been unsubscribed. The server may still deliver messages
+/// for it that were in flight when it received the UNSUBSCRIBE frame, and those are dropped.
+/// Subscription ids are never reused on a connection, so the marker is kept until it closes.
+pub(crate) type Routes = Arc<Mutex<HashMap<String, Option<MessageSender>>>>;
+
+/// Ids of the subscriptions whose messages must be acknowledged, i.e. those not using
+/// [`AckMode::Auto`](crate::AckMode::Auto).
+pub(crate) type Acknowledged = Arc<Mutex<HashSet<String>>>;
+
+/// A handle to an active subscription, yielding the messages the server delivers for it.
+///
+/// While the handle is alive, messages for its subscription are delivered only here. Once it is
+/// dropped, they are delivered through the client's own [`messages`](crate::NationalRailPushPortClient::messages)
+/// stream instead.
+#[derive(Debug)]
+pub struct Subscription {
+    id: String,
+    destination: String,
+    messages: MessageReceiver,
+    outbox: Outbox,
+    routes: Routes,
+}
+
+impl Subscription {
+    pub(crate) fn new(
+        id: String,
+        destination: String,
+        messages: MessageReceiver,
+        outbox: Outbox,
+        routes: Routes,
+    ) -> Self {
+        Self {
+            id,
+            destination,
+            messages,
+            outbox,
+            routes,
+        }
+    }
+
+    /// The subscription id sent to the server.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The destination subscribed to.
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Waits for the next message for this subscription.
+    ///
+    /// Returns `Ok(None)` once the connection has closed.
+    pub async fn next_message(&mut self) -> Result<Option<Message>, PushPortError> {
+        self.messages.recv().await.transpose()
+    }
+
+    /// Sends an UNSUBSCRIBE frame, so the server stops delivering messages for this subscription.
+    ///
+    /// Messages the server sent before processing the frame are dropped rather than delivered to
+    /// the client's own stream. The server processes frames in order, so a later
+    /// [`disconnect`](crate::NationalRailPushPortClient::disconnect) that receives its receipt also
+    /// confirms the unsubscribe.
+    pub async fn unsubscribe(self) -> Result<(), PushPortError> {
+        self.routes.lock().unwrap().insert(self.id.clone(), None);
+        let unsubscribe_frame = FrameBuilder::new("UNSUBSCRIBE")
+            .header("id", &self.id)
+            .build();
+        self.outbox
+            .send(unsubscribe_frame.encode())
+            .map_err(|_| PushPortError::ConnectionClosed)?;
+        Ok(())
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Result<Message, PushPortError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.messages.poll_recv(cx)
+    }
+}
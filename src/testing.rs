@@ -0,0 +1,457 @@
+//! An in-process STOMP broker for testing consumers without a real server.
+//!
+//! Enabled by the `testing` feature. The broker listens on a local port, accepts any credentials
+//! unless told to [reject them](MockBroker::reject_connect), answers frames that ask for a receipt,
+//! and lets tests script the frames it sends back:
+//!
+//! ```no_run
+//! use national_rail_push_port_client::testing::MockBroker;
+//! use national_rail_push_port_client::NationalRailPushPortClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let broker = MockBroker::start().await?;
+//! let mut client = NationalRailPushPortClient::connect_with(broker.connect_options()).await?;
+//! let mut subscription = client.subscribe("darwin.pushport-v16").await?;
+//!
+//! broker.send_gzipped_message("darwin.pushport-v16", "<Pport/>");
+//! let message = subscription.next_message().await?.unwrap();
+//! assert_eq!(message.body(), "<Pport/>");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+
+use crate::client::destination_path;
+use crate::frame::{compress_gzipped_data, parse_stomp_frame, skip_eols, FrameBuilder, StompFrame};
+use crate::options::ConnectOptions;
+
+/// How long [`MockBroker::expect_frame`] waits before failing the test.
+const EXPECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A scripted action for the broker's current connection.
+enum Command {
+    Message {
+        destination: String,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    },
+    Raw(Vec<u8>),
+    Disconnect,
+}
+
+/// The frames a client has sent to the broker.
+#[derive(Default)]
+struct Received {
+    all: Mutex<Vec<StompFrame>>,
+    unclaimed: Mutex<VecDeque<StompFrame>>,
+    arrived: Notify,
+}
+
+/// A client's subscription, as recorded from its SUBSCRIBE frame.
+struct Subscribed {
+    id: String,
+    destination: String,
+    ack: String,
+}
+
+/// The heart-beat intervals the broker offers, as (send, receive).
+type HeartBeat = Arc<Mutex<(Duration, Duration)>>;
+
+/// The `message` header of the ERROR frame the broker answers CONNECT with, if it rejects them.
+type Rejection = Arc<Mutex<Option<String>>>;
+
+/// A mock STOMP broker listening on a local port.
+///
+/// Connections are served one at a time, so a client that reconnects after
+/// [`disconnect`](Self::disconnect) is served by the same broker. Scripted frames go to the current
+/// connection, or wait for the next one if no client is connected. The broker stops when dropped.
+pub struct MockBroker {
+    port: u16,
+    commands: mpsc::UnboundedSender<Command>,
+    received: Arc<Received>,
+    heart_beat: HeartBeat,
+    rejection: Rejection,
+    server: JoinHandle<()>,
+}
+
+impl MockBroker {
+    /// Starts a broker on a free port on 127.0.0.1.
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let (commands, scripted) = mpsc::unbounded_channel();
+        let received = Arc::new(Received::default());
+        let heart_beat = HeartBeat::default();
+        let rejection = Rejection::default();
+        let server = tokio::spawn(serve(
+            listener,
+            scripted,
+            received.clone(),
+            heart_beat.clone(),
+            rejection.clone(),
+        ));
+        Ok(Self {
+            port,
+            commands,
+            received,
+            heart_beat,
+            rejection,
+            server,
+        })
+    }
+
+    /// The port the broker is listening on.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Options for connecting to the broker. Heart-beats are disabled, since the broker never
+    /// sends them.
+    pub fn connect_options(&self) -> ConnectOptions {
+        ConnectOptions::new("127.0.0.1", self.port, "user", "password")
+            .heart_beat(Duration::ZERO, Duration::ZERO)
+    }
+
+    /// Sets the heart-beat intervals the broker replies with in its CONNECTED frame, as (send,
+    /// receive). Applies to CONNECT frames received from now on. Defaults to zero in both
+    /// directions.
+    ///
+    /// The broker never actually sends heart-beats, so offering a send interval to a client that
+    /// wants to receive them simulates a server that has gone silent.
+    pub fn set_heart_beat(&self, send: Duration, receive: Duration) {
+        *self.heart_beat.lock().unwrap() = (send, receive);
+    }
+
+    /// Answers CONNECT frames received from now on with an ERROR frame carrying `message`, and
+    /// closes the connection, the way a server rejecting the credentials does.
+    pub fn reject_connect(&self, message: &str) {
+        *self.rejection.lock().unwrap() = Some(message.to_string());
+    }
+
+    /// Accepts CONNECT frames again after [`reject_connect`](Self::reject_connect).
+    pub fn accept_connect(&self) {
+        *self.rejection.lock().unwrap() = None;
+    }
+
+    /// Sends a MESSAGE with a plain body to every subscription to `destination`.
+    ///
+    /// Destinations are interpreted as in [`subscribe`](crate::NationalRailPushPortClient::subscribe).
+    /// If nothing has subscribed to the destination yet, the message and everything scripted after
+    /// it are held until something does.
+    pub fn send_message(&self, destination: &str, body: impl Into<Vec<u8>>) {
+        self.send_message_with(destination, Vec::new(), body);
+    }
+
+    /// Sends a MESSAGE with a gzipped body, the way Darwin does.
+    pub fn send_gzipped_message(&self, destination: &str, body: impl AsRef<[u8]>) {
+        let body = compress_gzipped_data(body.as_ref()).expect("gzip into memory cannot fail");
+        self.send_message_with(destination, Vec::new(), body);
+    }
+
+    /// Sends a MESSAGE with extra headers. `subscription`, `message-id`, `destination` and, for
+    /// subscriptions that acknowledge, `ack` are set by the broker.
+    pub fn send_message_with(
+        &self,
+        destination: &str,
+        headers: Vec<(String, String)>,
+        body: impl Into<Vec<u8>>,
+    ) {
+        self.script(Command::Message {
+            destination: destination_path(destination),
+            headers,
+            body: body.into(),
+        });
+    }
+
+    /// Sends an ERROR frame with the given `message` header and body.
+    pub fn send_error(&self, message: &str, body: &str) {
+        let frame = FrameBuilder::new("ERROR")
+            .header("message", message)
+            .body(body)
+            .build();
+        self.send_frame(&frame);
+    }
+
+    /// Sends an arbitrary frame.
+    pub fn send_frame(&self, frame: &StompFrame) {
+        self.script(Command::Raw(frame.encode()));
+    }
+
+    /// Sends raw bytes, e.g. heart-beats or a deliberately malformed frame.
+    pub fn send_raw(&self, data: impl Into<Vec<u8>>) {
+        self.script(Command::Raw(data.into()));
+    }
+
+    /// Closes the current connection once everything scripted before it has been sent.
+    pub fn disconnect(&self) {
+        self.script(Command::Disconnect);
+    }
+
+    /// Returns every frame clients have sent so far, in order, including CONNECT.
+    pub fn received_frames(&self) -> Vec<StompFrame> {
+        self.received.all.lock().unwrap().clone()
+    }
+
+    /// Waits for the client to send a frame with the given command, and returns it.
+    ///
+    /// Each frame is returned once, oldest first, so calling this twice with "SEND" returns the
+    /// first two SEND frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no such frame arrives within five seconds.
+    pub async fn expect_frame(&self, command: &str) -> StompFrame {
+        let wait = async {
+            loop {
+                let mut arrived = pin!(self.received.arrived.notified());
+                arrived.as_mut().enable();
+                if let Some(frame) = self.claim(command) {
+                    return frame;
+                }
+                arrived.await;
+            }
+        };
+        match tokio::time::timeout(EXPECT_TIMEOUT, wait).await {
+            Ok(frame) => frame,
+            Err(_) => {
+                let commands: Vec<String> = self
+                    .received_frames()
+                    .iter()
+                    .map(|frame| frame.command().to_string())
+                    .collect();
+                panic!(
+                    "no {} frame received within {:?}; received {:?}",
+                    command, EXPECT_TIMEOUT, commands
+                )
+            }
+        }
+    }
+
+    fn claim(&self, command: &str) -> Option<StompFrame> {
+        let mut unclaimed = self.received.unclaimed.lock().unwrap();
+        let index = unclaimed
+            .iter()
+            .position(|frame| frame.command() == command)?;
+        unclaimed.remove(index)
+    }
+
+    fn script(&self, command: Command) {
+        // The server task only stops when the broker is dropped.
+        let _ = self.commands.send(command);
+    }
+}
+
+impl Drop for MockBroker {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Serves connections one at a time until the broker is dropped.
+async fn serve(
+    listener: TcpListener,
+    mut scripted: mpsc::UnboundedReceiver<Command>,
+    received: Arc<Received>,
+    heart_beat: HeartBeat,
+    rejection: Rejection,
+) {
+    while let Ok((stream, _)) = listener.accept().await {
+        let connection = Connection {
+            stream,
+            heart_beat: heart_beat.clone(),
+            rejection: rejection.clone(),
+            subscriptions: Vec::new(),
+            held: VecDeque::new(),
+            next_message_id: 1,
+            connected: false,
+        };
+        if let Err(e) = serve_connection(connection, &mut scripted, &received).await {
+            eprintln!("Mock broker connection failed: {}", e);
+        }
+    }
+}
+
+async fn serve_connection(
+    mut connection: Connection,
+    scripted: &mut mpsc::UnboundedReceiver<Command>,
+    received: &Received,
+) -> std::io::Result<()> {
+    let mut accumulated = Vec::new();
+    let mut buf = vec![0u8; 8192];
+
+    loop {
+        tokio::select! {
+            n = connection.stream.read(&mut buf) => {
+                let n = n?;
+                if n == 0 {
+                    return Ok(());
+                }
+                accumulated.extend_from_slice(&buf[..n]);
+                while let Some((frame_len, frame)) = parse_stomp_frame(&accumulated)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?
+                {
+                    accumulated.drain(..frame_len);
+                    // Record the frame before answering it, so a test that has seen the answer
+                    // also sees the frame.
+                    received.all.lock().unwrap().push(frame.clone());
+                    received.unclaimed.lock().unwrap().push_back(frame.clone());
+                    received.arrived.notify_waiters();
+
+                    if !connection.handle(&frame).await? {
+                        return Ok(());
+                    }
+                }
+                accumulated.drain(..skip_eols(&accumulated));
+                // A new subscription may match messages that were waiting for one.
+                if !connection.release_held().await? {
+                    return Ok(());
+                }
+            }
+            Some(command) = scripted.recv(), if connection.connected => {
+                if !connection.run(command).await? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// The state of one client connection.
+struct Connection {
+    stream: TcpStream,
+    heart_beat: HeartBeat,
+    rejection: Rejection,
+    subscriptions: Vec<Subscribed>,
+    /// Scripted commands waiting behind a message that nothing is subscribed to yet.
+    held: VecDeque<Command>,
+    next_message_id: u64,
+    connected: bool,
+}
+
+impl Connection {
+    /// Answers a frame from the client.
+    ///
+    /// Returns `false` once the connection should be closed, after DISCONNECT or a rejected
+    /// CONNECT.
+    async fn handle(&mut self, frame: &StompFrame) -> std::io::Result<bool> {
+        match frame.command() {
+            "CONNECT" | "STOMP" => {
+                let rejection = self.rejection.lock().unwrap().clone();
+                if let Some(message) = rejection {
+                    let error = FrameBuilder::new("ERROR")
+                        .header("message", message)
+                        .build();
+                    self.stream.write_all(&error.encode()).await?;
+                    return Ok(false);
+                }
+                self.connected = true;
+                let (send, receive) = *self.heart_beat.lock().unwrap();
+                let connected = FrameBuilder::new("CONNECTED")
+                    .header("version", "1.2")
+                    .header(
+                        "heart-beat",
+                        format!("{},{}", send.as_millis(), receive.as_millis()),
+                    )
+                    .header("server", "mock-broker")
+                    .build();
+                self.stream.write_all(&connected.encode()).await?;
+            }
+            "SUBSCRIBE" => self.subscriptions.push(Subscribed {
+                id: frame.header("id").unwrap_or_default().to_string(),
+                destination: frame.header("destination").unwrap_or_default().to_string(),
+                ack: frame.header("ack").unwrap_or("auto").to_string(),
+            }),
+            "UNSUBSCRIBE" => self
+                .subscriptions
+                .retain(|subscribed| Some(subscribed.id.as_str()) != frame.header("id")),
+            _ => {}
+        }
+        if let Some(receipt_id) = frame.header("receipt") {
+            let receipt = FrameBuilder::new("RECEIPT")
+                .header("receipt-id", receipt_id)
+                .build();
+            self.stream.write_all(&receipt.encode()).await?;
+        }
+        Ok(frame.command() != "DISCONNECT")
+    }
+
+    /// Queues a scripted command behind any held ones, and runs whatever can run.
+    ///
+    /// Returns `false` once a scripted disconnect is reached.
+    async fn run(&mut self, command: Command) -> std::io::Result<bool> {
+        self.held.push_back(command);
+        self.release_held().await
+    }
+
+    /// Runs held commands in order, stopping at a message that nothing is subscribed to yet.
+    ///
+    /// Returns `false` once a scripted disconnect is reached.
+    async fn release_held(&mut self) -> std::io::Result<bool> {
+        while let Some(command) = self.held.pop_front() {
+            match command {
+                Command::Message {
+                    destination,
+                    headers,
+                    body,
+                } => {
+                    let matching: Vec<(String, bool)> = self
+                        .subscriptions
+                        .iter()
+                        .filter(|subscribed| subscribed.destination == destination)
+                        .map(|subscribed| (subscribed.id.clone(), subscribed.ack != "auto"))
+                        .collect();
+                    if matching.is_empty() {
+                        self.held.push_front(Command::Message {
+                            destination,
+                            headers,
+                            body,
+                        });
+                        break;
+                    }
+                    for (subscription_id, acknowledged) in matching {
+                        self.send_message(
+                            &subscription_id,
+                            acknowledged,
+                            &destination,
+                            &headers,
+                            &body,
+                        )
+                        .await?;
+                    }
+                }
+                Command::Raw(data) => self.stream.write_all(&data).await?,
+                Command::Disconnect => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    async fn send_message(
+        &mut self,
+        subscription_id: &str,
+        acknowledged: bool,
+        destination: &str,
+        headers: &[(String, String)],
+        body: &[u8],
+    ) -> std::io::Result<()> {
+        let message_id = format!("mock-{}", self.next_message_id);
+        self.next_message_id += 1;
+        let mut message = FrameBuilder::new("MESSAGE")
+            .header("subscription", subscription_id)
+            .header("message-id", &message_id)
+            .header("destination", destination);
+        if acknowledged {
+            message = message.header("ack", &message_id);
+        }
+        let message = message.headers(headers.to_vec()).body(body).build();
+        self.stream.write_all(&message.encode()).await
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::error::PushPortError;
+
+/// TLS settings, used by [`ConnectOptions::tls`](crate::ConnectOptions::tls).
+///
+/// By default the server's certificate is checked against the Mozilla root certificates from
+/// `webpki-roots`, and must be valid for the host the client connects to.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    root_certificates: Option<Vec<CertificateDer<'static>>>,
+    server_name: Option<String>,
+    config: Option<Arc<ClientConfig>>,
+}
+
+impl TlsOptions {
+    /// Creates options that trust the bundled Mozilla root certificates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts the given root certificates instead of the bundled ones, e.g. a private CA or a
+    /// self-signed test certificate. Can be called several times to trust more certificates.
+    pub fn root_certificates(
+        mut self,
+        certificates: impl IntoIterator<Item = CertificateDer<'static>>,
+    ) -> Self {
+        self.root_certificates
+            .get_or_insert_with(Vec::new)
+            .extend(certificates);
+        self
+    }
+
+    /// Trusts the root certificates in a PEM file instead of the bundled ones.
+    ///
+    /// Fails with [`PushPortError::Tls`] if the data holds no valid certificate.
+    pub fn root_certificates_pem(self, pem: &[u8]) -> Result<Self, PushPortError> {
+        let certificates = CertificateDer::pem_slice_iter(pem)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PushPortError::Tls(format!("invalid PEM certificate: {}", e)))?;
+        if certificates.is_empty() {
+            return Err(PushPortError::Tls(
+                "no certificates found in PEM data".to_string(),
+            ));
+        }
+        Ok(self.root_certificates(certificates))
+    }
+
+    /// Sets the name sent with SNI and checked against the server's certificate, when it differs
+    /// from the host connected to, e.g. when connecting by IP address.
+    pub fn server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = Some(server_name.into());
+        self
+    }
+
+    /// Uses a complete rustls configuration, e.g. for client certificates. Root certificates set
+    /// on these options are then ignored.
+    pub fn client_config(mut self, config: Arc<ClientConfig>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    fn connector(&self) -> Result<TlsConnector, PushPortError> {
+        if let Some(config) = &self.config {
+            return Ok(TlsConnector::from(config.clone()));
+        }
+
+        let mut roots = RootCertStore::empty();
+        match &self.root_certificates {
+            Some(certificates) => {
+                for certificate in certificates {
+                    roots.add(certificate.clone()).map_err(|e| {
+                        PushPortError::Tls(format!("invalid root certificate: {}", e))
+                    })?;
+                }
+            }
+            None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+        }
+        // Use ring explicitly, so a different default provider elsewhere in the program can't clash.
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| PushPortError::Tls(e.to_string()))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(TlsConnector::from(Arc::new(config)))
+    }
+}
+
+/// Performs a TLS handshake over an open TCP connection to `host`.
+pub(crate) async fn connect_tls(
+    stream: TcpStream,
+    host: &str,
+    options: &TlsOptions,
+) -> Result<TlsStream<TcpStream>, PushPortError> {
+    let name = options.server_name.as_deref().unwrap_or(host);
+    let server_name = ServerName::try_from(name.to_string())
+        .map_err(|e| PushPortError::Tls(format!("invalid server name {}: {}", name, e)))?;
+    options
+        .connector()?
+        .connect(server_name, stream)
+        .await
+        .map_err(|e| match e.kind() {
+            // rustls reports rejected certificates and protocol failures as invalid data.
+            std::io::ErrorKind::InvalidData => PushPortError::Tls(e.to_string()),
+            _ => PushPortError::Io(e),
+        })
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use crate::client::destination_path;
+use crate::error::PushPortError;
+use crate::frame::{FrameBuilder, StompFrame};
+use crate::heartbeat::Outbox;
+use crate::message::Message;
+use crate::receipt::Receipts;
+
+/// A STOMP transaction, started with [`NationalRailPushPortClient::begin`](crate::NationalRailPushPortClient::begin).
+///
+/// Sends and acknowledgements made through the transaction take effect together when it is
+/// committed, or not at all if it is aborted. A transaction dropped without being committed is
+/// aborted.
+#[derive(Debug)]
+pub struct Transaction {
+    id: String,
+    outbox: Outbox,
+    receipts: Receipts,
+    receipt_timeout: Duration,
+    finished: bool,
+}
+
+impl Transaction {
+    pub(crate) fn new(
+        id: String,
+        outbox: Outbox,
+        receipts: Receipts,
+        receipt_timeout: Duration,
+    ) -> Self {
+        Self {
+            id,
+            outbox,
+            receipts,
+            receipt_timeout,
+            finished: false,
+        }
+    }
+
+    /// The transaction id sent to the server.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Sends a message as part of the transaction.
+    ///
+    /// Destinations and headers are handled as in [`NationalRailPushPortClient::send`](crate::NationalRailPushPortClient::send).
+    pub async fn send(
+        &mut self,
+        destination: &str,
+        headers: &[(&str, &str)],
+        body: impl Into<Vec<u8>>,
+    ) -> Result<(), PushPortError> {
+        let send_frame = FrameBuilder::new("SEND")
+            .header("destination", destination_path(destination))
+            .header("transaction", &self.id)
+            .headers(headers.iter().copied())
+            .body(body)
+            .build();
+        self.send_frame(&send_frame)
+    }
+
+    /// Acknowledges a message as part of the transaction.
+    ///
+    /// Does nothing for messages from auto-acknowledged subscriptions.
+    pub async fn ack(&mut self, message: &Message) -> Result<(), PushPortError> {
+        let Some(ack_id) = message.ack_id() else {
+            return Ok(());
+        };
+        let ack_frame = FrameBuilder::new("ACK")
+            .header("id", ack_id)
+            .header("transaction", &self.id)
+            .build();
+        self.send_frame(&ack_frame)
+    }
+
+    /// Commits the transaction, waiting for the server to confirm it with a RECEIPT, up to the
+    /// configured receipt timeout.
+    pub async fn commit(mut self) -> Result<(), PushPortError> {
+        self.finished = true;
+        let receipt_id = format!("commit-{}", self.id);
+        let receipt = self.receipts.register(&receipt_id);
+
+        let commit_frame = FrameBuilder::new("COMMIT")
+            .header("transaction", &self.id)
+            .header("receipt", &receipt_id)
+            .build();
+        if let Err(e) = self.send_frame(&commit_frame) {
+            self.receipts.cancel(&receipt_id);
+            return Err(e);
+        }
+        receipt.wait(self.receipt_timeout).await
+    }
+
+    /// Aborts the transaction, discarding its sends and acknowledgements.
+    pub async fn abort(mut self) -> Result<(), PushPortError> {
+        self.finished = true;
+        self.send_frame(&abort_frame(&self.id))
+    }
+
+    fn send_frame(&self, frame: &StompFrame) -> Result<(), PushPortError> {
+        self.outbox
+            .send(frame.encode())
+            .map_err(|_| PushPortError::ConnectionClosed)
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            // If the connection has already closed, the server discards the transaction anyway.
+            let _ = self.send_frame(&abort_frame(&self.id));
+        }
+    }
+}
+
+fn abort_frame(transaction_id: &str) -> StompFrame {
+    FrameBuilder::new("ABORT")
+        .header("transaction", transaction_id)
+        .build()
+}
@@ -0,0 +1,9 @@
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// A byte stream the client can run over, such as a `TcpStream`, a TLS stream, a Unix socket or
+/// one end of a `tokio::io::duplex` pair.
+///
+/// Implemented for every type that meets the bounds, so it never needs implementing by hand.
+pub trait Transport: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+
+impl<T> Transport for T where T: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
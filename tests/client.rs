@@ -0,0 +1,831 @@
+use futures::StreamExt;
+use std::pin::pin;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+use national_rail_push_port_client::testing::MockBroker;
+use national_rail_push_port_client::{
+    AckMode, Backoff, ConnectOptions, ConnectionEvent, FrameBuilder, NationalRailPushPortClient,
+    PushPortError, ReconnectingClient, SendOptions, SubscribeOptions,
+};
+
+const TOPIC: &str = "darwin.pushport-v16";
+
+const TRAIN_STATUS: &str = r#"<Pport xmlns="http://www.thalesgroup.com/rtti/PushPort/v16" xmlns:ns5="http://www.thalesgroup.com/rtti/PushPort/Forecasts/v3" ts="2024-05-01T10:00:00.0000000+01:00" version="16.0"><uR updateOrigin="TD"><TS rid="202405018000001" uid="C12345" ssd="2024-05-01"><ns5:Location tpl="EUSTON" wtd="10:00" ptd="10:00"><ns5:dep et="10:02" src="Darwin"/></ns5:Location></TS></uR></Pport>"#;
+
+async fn connect(broker: &MockBroker) -> NationalRailPushPortClient {
+    NationalRailPushPortClient::connect_with(broker.connect_options())
+        .await
+        .expect("connect to mock broker")
+}
+
+#[tokio::test]
+async fn connect_sends_credentials_and_reads_connected() {
+    let broker = MockBroker::start().await.unwrap();
+    let client = connect(&broker).await;
+
+    let connect_frame = broker.expect_frame("CONNECT").await;
+    assert_eq!(connect_frame.header("accept-version"), Some("1.2"));
+    assert_eq!(connect_frame.header("login"), Some("user"));
+    assert_eq!(connect_frame.header("passcode"), Some("password"));
+    assert_eq!(connect_frame.header("heart-beat"), Some("0,0"));
+
+    let info = client.connection_info();
+    assert_eq!(info.version, "1.2");
+    assert_eq!(info.server.as_deref(), Some("mock-broker"));
+}
+
+#[tokio::test]
+async fn connect_options_set_virtual_host_and_accept_version() {
+    let broker = MockBroker::start().await.unwrap();
+    let options = broker
+        .connect_options()
+        .virtual_host("darwin-dist")
+        .accept_version("1.1,1.2")
+        .client_id("consumer-1")
+        .tcp_keepalive(Duration::from_secs(60))
+        .read_buffer_size(16)
+        .header("x-team", "timetables");
+    let mut client = NationalRailPushPortClient::connect_with(options)
+        .await
+        .unwrap();
+
+    let connect_frame = broker.expect_frame("CONNECT").await;
+    assert_eq!(connect_frame.header("host"), Some("darwin-dist"));
+    assert_eq!(connect_frame.header("accept-version"), Some("1.1,1.2"));
+    assert_eq!(connect_frame.header("client-id"), Some("consumer-1"));
+    assert_eq!(connect_frame.header("x-team"), Some("timetables"));
+
+    // Frames longer than the read buffer are reassembled.
+    client.subscribe(TOPIC).await.unwrap();
+    broker.send_message(TOPIC, "a body longer than sixteen bytes");
+    let message = client.next_message().await.unwrap().unwrap();
+    assert_eq!(message.body(), "a body longer than sixteen bytes");
+}
+
+#[tokio::test]
+async fn connect_times_out_when_server_does_not_answer() {
+    // The listener accepts TCP connections but never replies to CONNECT.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let options = ConnectOptions::new("127.0.0.1", port, "user", "password")
+        .connect_timeout(Some(Duration::from_millis(100)));
+
+    let result = NationalRailPushPortClient::connect_with(options).await;
+    assert!(matches!(result, Err(PushPortError::ConnectTimeout(_))));
+}
+
+#[tokio::test]
+async fn subscribe_sends_destination_ack_mode_and_durable_name() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+
+    let first = client.subscribe(TOPIC).await.unwrap();
+    let second = client
+        .subscribe_with(
+            "/queue/archive",
+            SubscribeOptions::new()
+                .ack(AckMode::ClientIndividual)
+                .durable("archiver")
+                .header("selector", "type = 'TS'"),
+        )
+        .await
+        .unwrap();
+
+    let frame = broker.expect_frame("SUBSCRIBE").await;
+    assert_eq!(frame.header("id"), Some(first.id()));
+    assert_eq!(
+        frame.header("destination"),
+        Some("/topic/darwin.pushport-v16")
+    );
+    assert_eq!(frame.header("ack"), Some("auto"));
+
+    let frame = broker.expect_frame("SUBSCRIBE").await;
+    assert_eq!(frame.header("id"), Some(second.id()));
+    assert_eq!(frame.header("destination"), Some("/queue/archive"));
+    assert_eq!(frame.header("ack"), Some("client-individual"));
+    assert_eq!(frame.header("activemq.subscriptionName"), Some("archiver"));
+    assert_eq!(frame.header("selector"), Some("type = 'TS'"));
+    assert_ne!(first.id(), second.id());
+}
+
+#[tokio::test]
+async fn read_messages_decodes_plain_and_gzipped_bodies() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    client.subscribe(TOPIC).await.unwrap();
+
+    broker.send_message(TOPIC, "plain body");
+    broker.send_gzipped_message(TOPIC, "gzipped body");
+    broker.send_message(TOPIC, "");
+    broker.disconnect();
+
+    let mut bodies = Vec::new();
+    client
+        .read_messages(|body| {
+            bodies.push(body);
+            Ok(())
+        })
+        .await
+        .unwrap();
+    assert_eq!(bodies, ["plain body", "gzipped body", ""]);
+}
+
+#[tokio::test]
+async fn read_pport_deserializes_darwin_documents() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    client.subscribe(TOPIC).await.unwrap();
+
+    broker.send_gzipped_message(TOPIC, TRAIN_STATUS);
+    broker.send_message(TOPIC, "");
+    broker.disconnect();
+
+    let mut documents = Vec::new();
+    client
+        .read_pport(|pport| {
+            documents.push(pport);
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(documents.len(), 1);
+    let response = documents[0].response().unwrap();
+    let status = &response.train_status[0];
+    assert_eq!(status.rid, "202405018000001");
+    assert_eq!(status.locations[0].tpl, "EUSTON");
+    let departure = status.locations[0].dep.as_ref().unwrap();
+    assert_eq!(departure.et.as_deref(), Some("10:02"));
+}
+
+#[tokio::test]
+async fn callback_errors_stop_reading() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    client.subscribe(TOPIC).await.unwrap();
+    broker.send_message(TOPIC, "first");
+    broker.send_message(TOPIC, "second");
+
+    let mut calls = 0;
+    let result = client
+        .read_messages(|_| {
+            calls += 1;
+            Err("storage unavailable".into())
+        })
+        .await;
+    assert!(matches!(result, Err(PushPortError::Callback(_))));
+    assert_eq!(calls, 1);
+}
+
+#[tokio::test]
+async fn undecodable_messages_do_not_end_the_stream() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    client.subscribe(TOPIC).await.unwrap();
+    let corrupt_gzip = vec![0x1f, 0x8b, 0x08, 0x00, 0xde, 0xad];
+
+    broker.send_message(TOPIC, corrupt_gzip.clone());
+    broker.send_message(TOPIC, "after corrupt gzip");
+    {
+        let mut messages = pin!(client.messages());
+        assert!(matches!(
+            messages.next().await,
+            Some(Err(PushPortError::Decompression(_)))
+        ));
+        let message = messages.next().await.unwrap().unwrap();
+        assert_eq!(message.body(), "after corrupt gzip");
+    }
+
+    broker.send_message(TOPIC, corrupt_gzip);
+    broker.send_gzipped_message(TOPIC, "<Pport><unclosed>");
+    broker.send_gzipped_message(TOPIC, TRAIN_STATUS);
+    broker.disconnect();
+    let mut documents = Vec::new();
+    client
+        .read_pport(|pport| {
+            documents.push(pport);
+            Ok(())
+        })
+        .await
+        .unwrap();
+    assert_eq!(documents.len(), 1);
+}
+
+#[tokio::test]
+async fn full_buffers_hold_messages_until_they_are_consumed() {
+    let broker = MockBroker::start().await.unwrap();
+    let options = broker.connect_options().message_capacity(2);
+    let mut client = NationalRailPushPortClient::connect_with(options)
+        .await
+        .unwrap();
+    let mut subscription = client.subscribe(TOPIC).await.unwrap();
+    for i in 0..20 {
+        broker.send_message(TOPIC, format!("message {}", i));
+    }
+    // Let the messages pile up while nothing reads the subscription.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    for i in 0..20 {
+        let message = subscription.next_message().await.unwrap().unwrap();
+        assert_eq!(message.body(), format!("message {}", i));
+    }
+}
+
+#[tokio::test]
+async fn unread_messages_for_dropped_handles_do_not_stall_live_ones() {
+    let broker = MockBroker::start().await.unwrap();
+    let options = broker.connect_options().message_capacity(2);
+    let mut client = NationalRailPushPortClient::connect_with(options)
+        .await
+        .unwrap();
+    let mut kept = client.subscribe("a").await.unwrap();
+    drop(client.subscribe("b").await.unwrap());
+
+    for i in 0..5 {
+        broker.send_message("b", format!("b{}", i));
+    }
+    broker.send_message("a", "a0");
+    let message = tokio::time::timeout(Duration::from_secs(2), kept.next_message())
+        .await
+        .expect("message for the live handle")
+        .unwrap()
+        .unwrap();
+    assert_eq!(message.body(), "a0");
+
+    broker.disconnect();
+    let end = tokio::time::timeout(Duration::from_secs(2), kept.next_message())
+        .await
+        .expect("live handle sees the connection close");
+    assert!(matches!(end, Ok(None)));
+}
+
+#[tokio::test]
+async fn escaped_headers_are_decoded() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    let mut subscription = client.subscribe(TOPIC).await.unwrap();
+
+    broker.send_message_with(
+        TOPIC,
+        vec![("note".to_string(), "a:b\\c\nd".to_string())],
+        "body",
+    );
+    let message = subscription.next_message().await.unwrap().unwrap();
+    assert_eq!(message.header("note"), Some("a:b\\c\nd"));
+    assert_eq!(message.destination(), Some("/topic/darwin.pushport-v16"));
+}
+
+#[tokio::test]
+async fn crlf_frames_and_heart_beats_are_parsed() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    broker.expect_frame("CONNECT").await;
+
+    broker.send_raw("\n\r\nMESSAGE\r\nmessage-id:1\r\ncontent-length:5\r\n\r\nfirst\0\n\n");
+    broker.send_raw("MESSAGE\nmessage-id:2\n\nsec");
+    broker.send_raw("ond\0");
+
+    let first = client.next_message().await.unwrap().unwrap();
+    assert_eq!(first.message_id(), Some("1"));
+    assert_eq!(first.body(), "first");
+    let second = client.next_message().await.unwrap().unwrap();
+    assert_eq!(second.message_id(), Some("2"));
+    assert_eq!(second.body(), "second");
+}
+
+#[tokio::test]
+async fn silent_server_is_reported_as_heartbeat_timeout() {
+    let broker = MockBroker::start().await.unwrap();
+    broker.set_heart_beat(Duration::from_millis(50), Duration::ZERO);
+    let options = broker
+        .connect_options()
+        .heart_beat(Duration::ZERO, Duration::from_millis(20))
+        .heartbeat_grace(2.0);
+    let mut client = NationalRailPushPortClient::connect_with(options)
+        .await
+        .unwrap();
+    assert_eq!(client.connection_info().heart_beat, (50, 0));
+
+    let result = client.next_message().await;
+    assert!(
+        matches!(result, Err(PushPortError::HeartbeatTimeout(limit)) if limit == Duration::from_millis(100)),
+        "{:?}",
+        result
+    );
+}
+
+#[tokio::test]
+async fn error_frames_are_reported_as_server_errors() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    let mut subscription = client.subscribe(TOPIC).await.unwrap();
+
+    broker.send_error("subscription not permitted", "no access to topic");
+    match subscription.next_message().await {
+        Err(PushPortError::ServerError { message, body, .. }) => {
+            assert_eq!(message.as_deref(), Some("subscription not permitted"));
+            assert_eq!(body, "no access to topic");
+        }
+        other => panic!("expected a server error, got {:?}", other),
+    }
+    assert!(matches!(
+        client.next_message().await,
+        Err(PushPortError::ServerError { .. })
+    ));
+}
+
+#[tokio::test]
+async fn server_disconnect_ends_message_stream() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    client.subscribe("/queue/updates").await.unwrap();
+    broker.send_message("/queue/updates", "only");
+    broker.disconnect();
+
+    let mut messages = pin!(client.messages());
+    let bodies: Vec<String> = (&mut messages)
+        .map(|message| message.unwrap().into_body())
+        .collect()
+        .await;
+    assert_eq!(bodies, ["only"]);
+}
+
+#[tokio::test]
+async fn ack_and_nack_use_the_message_ack_header() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    let mut subscription = client
+        .subscribe_with(TOPIC, SubscribeOptions::new().ack(AckMode::Client))
+        .await
+        .unwrap();
+    broker.send_message(TOPIC, "one");
+    broker.send_message(TOPIC, "two");
+
+    let one = subscription.next_message().await.unwrap().unwrap();
+    let two = subscription.next_message().await.unwrap().unwrap();
+    one.nack().unwrap();
+    two.ack().unwrap();
+
+    let nack = broker.expect_frame("NACK").await;
+    assert_eq!(nack.header("id"), one.ack_id());
+    let ack = broker.expect_frame("ACK").await;
+    assert_eq!(ack.header("id"), two.ack_id());
+}
+
+#[tokio::test]
+async fn ack_uses_message_id_and_subscription_without_an_ack_header() {
+    // A STOMP 1.1 server, which does not send ack headers.
+    let (client_end, mut server_end) = tokio::io::duplex(64 * 1024);
+    let server = tokio::spawn(async move {
+        read_raw_frame(&mut server_end).await;
+        server_end
+            .write_all(b"CONNECTED\nversion:1.1\n\n\0")
+            .await
+            .unwrap();
+        read_raw_frame(&mut server_end).await;
+        server_end
+            .write_all(
+                b"MESSAGE\nsubscription:sub-1\nmessage-id:m-1\ndestination:/topic/x\n\none\0",
+            )
+            .await
+            .unwrap();
+        server_end
+            .write_all(b"MESSAGE\nsubscription:sub-1\ndestination:/topic/x\n\ntwo\0")
+            .await
+            .unwrap();
+        let ack = read_raw_frame(&mut server_end).await;
+        (ack, server_end)
+    });
+
+    let options = ConnectOptions::new("darwin.example", 61613, "user", "secret")
+        .heart_beat(Duration::ZERO, Duration::ZERO);
+    let mut client = NationalRailPushPortClient::from_stream(client_end, options)
+        .await
+        .unwrap();
+    let mut subscription = client
+        .subscribe_with("x", SubscribeOptions::new().ack(AckMode::ClientIndividual))
+        .await
+        .unwrap();
+    assert_eq!(subscription.id(), "sub-1");
+
+    let one = subscription.next_message().await.unwrap().unwrap();
+    one.ack().unwrap();
+    let (ack, _server_end) = server.await.unwrap();
+    assert!(ack.starts_with("ACK\n"));
+    assert!(ack.contains("\nmessage-id:m-1\n"));
+    assert!(ack.contains("\nsubscription:sub-1\n"));
+
+    // Without a message id there is nothing to acknowledge it by.
+    let two = subscription.next_message().await.unwrap().unwrap();
+    assert!(matches!(
+        two.nack(),
+        Err(PushPortError::InvalidHeader { .. })
+    ));
+}
+
+#[tokio::test]
+async fn ack_does_nothing_for_auto_acknowledged_messages() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    let mut subscription = client.subscribe(TOPIC).await.unwrap();
+    broker.send_message(TOPIC, "one");
+
+    let one = subscription.next_message().await.unwrap().unwrap();
+    one.ack().unwrap();
+    one.nack().unwrap();
+    client.disconnect().await.unwrap();
+    assert!(broker
+        .received_frames()
+        .iter()
+        .all(|f| f.command() != "ACK" && f.command() != "NACK"));
+}
+
+#[tokio::test]
+async fn send_encodes_headers_and_gzips_with_receipt() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+
+    client
+        .send("/queue/replay", &[("persistent", "true")], "<Pport/>")
+        .await
+        .unwrap();
+    client
+        .send_with(
+            "/queue/replay",
+            "compressed",
+            SendOptions::new().gzip().receipt(),
+        )
+        .await
+        .unwrap();
+
+    let plain = broker.expect_frame("SEND").await;
+    assert_eq!(plain.header("destination"), Some("/queue/replay"));
+    assert_eq!(plain.header("persistent"), Some("true"));
+    assert_eq!(plain.header("content-length"), Some("8"));
+    assert_eq!(plain.body(), b"<Pport/>");
+
+    let gzipped = broker.expect_frame("SEND").await;
+    assert!(gzipped.header("receipt").is_some());
+    assert_eq!(&gzipped.body()[..2], [0x1f, 0x8b]);
+}
+
+#[tokio::test]
+async fn transactions_commit_and_abort_on_drop() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+
+    let mut transaction = client.begin().await.unwrap();
+    let id = transaction.id().to_string();
+    transaction
+        .send("/queue/derived", &[], "derived")
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+
+    let abandoned = client.begin().await.unwrap();
+    let abandoned_id = abandoned.id().to_string();
+    drop(abandoned);
+
+    assert_eq!(
+        broker.expect_frame("BEGIN").await.header("transaction"),
+        Some(id.as_str())
+    );
+    let send = broker.expect_frame("SEND").await;
+    assert_eq!(send.header("transaction"), Some(id.as_str()));
+    assert_eq!(
+        broker.expect_frame("COMMIT").await.header("transaction"),
+        Some(id.as_str())
+    );
+    let abort = broker.expect_frame("ABORT").await;
+    assert_eq!(abort.header("transaction"), Some(abandoned_id.as_str()));
+}
+
+#[tokio::test]
+async fn disconnect_waits_for_receipt() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    let subscription = client.subscribe(TOPIC).await.unwrap();
+    subscription.unsubscribe().await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let commands: Vec<String> = broker
+        .received_frames()
+        .iter()
+        .map(|frame| frame.command().to_string())
+        .collect();
+    assert_eq!(
+        commands,
+        ["CONNECT", "SUBSCRIBE", "UNSUBSCRIBE", "DISCONNECT"]
+    );
+}
+
+#[tokio::test]
+async fn messages_arriving_after_unsubscribe_are_dropped() {
+    let broker = MockBroker::start().await.unwrap();
+    let mut client = connect(&broker).await;
+    let subscription = client.subscribe(TOPIC).await.unwrap();
+    let id = subscription.id().to_string();
+    subscription.unsubscribe().await.unwrap();
+    drop(client.subscribe("other").await.unwrap());
+
+    // A message the server sent before it processed the UNSUBSCRIBE frame.
+    let late = FrameBuilder::new("MESSAGE")
+        .header("subscription", &id)
+        .header("message-id", "late-1")
+        .header("destination", "/topic/darwin.pushport-v16")
+        .body("late")
+        .build();
+    broker.send_frame(&late);
+    broker.send_message("other", "next");
+
+    let message = client.next_message().await.unwrap().unwrap();
+    assert_eq!(message.body(), "next");
+}
+
+#[tokio::test]
+async fn reconnecting_client_resubscribes_after_disconnect() {
+    let broker = MockBroker::start().await.unwrap();
+    let backoff = Backoff {
+        initial: Duration::from_millis(10),
+        max: Duration::from_millis(50),
+        ..Backoff::default()
+    };
+    let mut client = ReconnectingClient::connect(broker.connect_options(), backoff)
+        .await
+        .unwrap();
+    let mut events = client.events();
+    client.subscribe(TOPIC).await.unwrap();
+
+    broker.send_message(TOPIC, "before");
+    assert_eq!(client.next_message().await.unwrap().body(), "before");
+
+    broker.disconnect();
+    broker.send_message(TOPIC, "after");
+    assert_eq!(client.next_message().await.unwrap().body(), "after");
+
+    assert_eq!(events.recv().await.unwrap(), ConnectionEvent::Disconnected);
+    assert_eq!(
+        broker
+            .received_frames()
+            .iter()
+            .filter(|f| f.command() == "SUBSCRIBE")
+            .count(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn reconnecting_client_restores_subscriptions_made_while_disconnected() {
+    let broker = MockBroker::start().await.unwrap();
+    let backoff = Backoff {
+        initial: Duration::from_millis(10),
+        max: Duration::from_millis(50),
+        ..Backoff::default()
+    };
+    let mut client = ReconnectingClient::connect(broker.connect_options(), backoff)
+        .await
+        .unwrap();
+
+    broker.disconnect();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while client.is_connected() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("the client should notice the connection has closed");
+    assert!(client.subscribe(TOPIC).await.is_err());
+
+    broker.send_message(TOPIC, "after");
+    assert_eq!(client.next_message().await.unwrap().body(), "after");
+    let subscribe = broker.expect_frame("SUBSCRIBE").await;
+    assert_eq!(
+        subscribe.header("destination"),
+        Some("/topic/darwin.pushport-v16")
+    );
+}
+
+#[tokio::test]
+async fn reconnecting_client_retries_when_the_new_connection_drops() {
+    let broker = MockBroker::start().await.unwrap();
+    let backoff = Backoff {
+        initial: Duration::from_millis(10),
+        max: Duration::from_millis(50),
+        ..Backoff::default()
+    };
+    let mut client = ReconnectingClient::connect(broker.connect_options(), backoff)
+        .await
+        .unwrap();
+    client.subscribe(TOPIC).await.unwrap();
+
+    // The second connection closes as soon as it is established, possibly while the subscription
+    // is being restored.
+    broker.disconnect();
+    broker.disconnect();
+    broker.send_message(TOPIC, "after");
+    assert_eq!(client.next_message().await.unwrap().body(), "after");
+}
+
+#[tokio::test]
+async fn rejected_credentials_are_reported_as_authentication_failures() {
+    let broker = MockBroker::start().await.unwrap();
+    broker.reject_connect("Invalid login or password");
+
+    let result = NationalRailPushPortClient::connect_with(broker.connect_options()).await;
+    match result {
+        Err(PushPortError::AuthenticationFailed { message }) => {
+            assert_eq!(message, "Invalid login or password")
+        }
+        other => panic!("expected AuthenticationFailed, got {:?}", other.err()),
+    }
+
+    broker.accept_connect();
+    connect(&broker).await;
+}
+
+#[tokio::test]
+async fn reconnecting_client_does_not_retry_rejected_credentials() {
+    let broker = MockBroker::start().await.unwrap();
+    let backoff = Backoff {
+        initial: Duration::from_millis(10),
+        max: Duration::from_millis(50),
+        ..Backoff::default()
+    };
+    let mut client = ReconnectingClient::connect(broker.connect_options(), backoff)
+        .await
+        .unwrap();
+
+    broker.reject_connect("Invalid login or password");
+    broker.disconnect();
+    let result = tokio::time::timeout(Duration::from_secs(5), client.next_message())
+        .await
+        .expect("rejected credentials should not be retried");
+    assert!(matches!(
+        result,
+        Err(PushPortError::AuthenticationFailed { .. })
+    ));
+    assert_eq!(
+        broker
+            .received_frames()
+            .iter()
+            .filter(|f| f.command() == "CONNECT")
+            .count(),
+        2
+    );
+}
+
+/// Reads one NUL-terminated frame from the server end of an in-memory connection.
+async fn read_raw_frame(stream: &mut (impl AsyncRead + Unpin)) -> String {
+    let mut frame = Vec::new();
+    loop {
+        let byte = stream.read_u8().await.expect("read frame");
+        if byte == 0 {
+            return String::from_utf8(frame).expect("UTF-8 frame");
+        }
+        frame.push(byte);
+    }
+}
+
+#[tokio::test]
+async fn from_stream_connects_over_a_duplex_pair() {
+    let (client_end, mut server_end) = tokio::io::duplex(64 * 1024);
+    let server = tokio::spawn(async move {
+        let connect = read_raw_frame(&mut server_end).await;
+        server_end
+            .write_all(b"CONNECTED\nversion:1.2\nserver:duplex\n\n\0")
+            .await
+            .unwrap();
+        server_end
+            .write_all(
+                b"MESSAGE\nsubscription:sub-1\nmessage-id:1\ndestination:/topic/x\n\nhello\0",
+            )
+            .await
+            .unwrap();
+        (connect, server_end)
+    });
+
+    let options = ConnectOptions::new("darwin.example", 61613, "user", "secret")
+        .heart_beat(Duration::ZERO, Duration::ZERO);
+    let mut client = NationalRailPushPortClient::from_stream(client_end, options)
+        .await
+        .unwrap();
+    let (connect, _server_end) = server.await.unwrap();
+
+    assert!(connect.starts_with("CONNECT\n"));
+    assert!(connect.contains("\nhost:darwin.example\n"));
+    assert!(connect.contains("\nlogin:user\n"));
+    assert_eq!(client.connection_info().server.as_deref(), Some("duplex"));
+    let message = client.next_message().await.unwrap().unwrap();
+    assert_eq!(message.body(), "hello");
+}
+
+/// Connects over a duplex pair to a server that answers CONNECT with `reply`.
+async fn handshake_with_reply(
+    reply: &'static [u8],
+) -> Result<NationalRailPushPortClient, PushPortError> {
+    let (client_end, mut server_end) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        read_raw_frame(&mut server_end).await;
+        server_end.write_all(reply).await.unwrap();
+        // Keep the connection open until the client has read the reply.
+        let _ = server_end.read_u8().await;
+    });
+    let options = ConnectOptions::new("darwin.example", 61613, "user", "secret")
+        .connect_timeout(Some(Duration::from_secs(5)));
+    NationalRailPushPortClient::from_stream(client_end, options).await
+}
+
+#[tokio::test]
+async fn handshake_errors_mentioning_credentials_are_authentication_failures() {
+    let replies: [(&[u8], &str); 6] = [
+        (b"ERROR\nmessage:Invalid password\n\n\0", "Invalid password"),
+        (b"ERROR\nmessage:Bad Credentials\n\n\0", "Bad Credentials"),
+        (
+            b"ERROR\nmessage:User is not authenticated\n\n\0",
+            "User is not authenticated",
+        ),
+        (b"ERROR\nmessage:LOGIN refused\n\n\0", "LOGIN refused"),
+        (
+            b"ERROR\nmessage:java.lang.SecurityException\n\n\0",
+            "java.lang.SecurityException",
+        ),
+        (
+            b"ERROR\n\nAuthentication failed for user\0",
+            "Authentication failed for user",
+        ),
+    ];
+    for (reply, expected) in replies {
+        match handshake_with_reply(reply).await {
+            Err(PushPortError::AuthenticationFailed { message }) => assert_eq!(message, expected),
+            other => panic!("expected AuthenticationFailed, got {:?}", other.err()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn handshake_errors_without_a_credentials_hint_are_rejections() {
+    let result =
+        handshake_with_reply(b"ERROR\nmessage:Too many connections\nretry:later\n\nTry again\0")
+            .await;
+    match result {
+        Err(PushPortError::HandshakeRejected {
+            message,
+            headers,
+            body,
+        }) => {
+            assert_eq!(message.as_deref(), Some("Too many connections"));
+            assert!(headers.contains(&("retry".to_string(), "later".to_string())));
+            assert_eq!(body, "Try again");
+        }
+        other => panic!("expected HandshakeRejected, got {:?}", other.err()),
+    }
+}
+
+#[tokio::test]
+async fn handshake_replies_other_than_connected_are_parse_errors() {
+    let result = handshake_with_reply(b"RECEIPT\nreceipt-id:1\n\n\0").await;
+    assert!(matches!(result, Err(PushPortError::FrameParse(_))));
+}
+
+#[tokio::test]
+async fn failed_writes_end_the_connection_with_an_io_error() {
+    // Frames from the server arrive on one pipe and frames from the client leave on another, so
+    // that writes can fail while reads still work.
+    let (incoming, mut server_writes) = tokio::io::duplex(64 * 1024);
+    let (outgoing, mut server_reads) = tokio::io::duplex(64 * 1024);
+    let server = tokio::spawn(async move {
+        read_raw_frame(&mut server_reads).await;
+        server_writes
+            .write_all(b"CONNECTED\nversion:1.2\n\n\0")
+            .await
+            .unwrap();
+        server_writes
+    });
+
+    let options = ConnectOptions::new("darwin.example", 61613, "user", "secret")
+        .heart_beat(Duration::ZERO, Duration::ZERO);
+    let stream = tokio::io::join(incoming, outgoing);
+    let mut client = NationalRailPushPortClient::from_stream(stream, options)
+        .await
+        .unwrap();
+    // The server has stopped reading, so the next write fails.
+    let _server_writes = server.await.unwrap();
+
+    client.subscribe(TOPIC).await.unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(5), client.next_message())
+        .await
+        .expect("the failed write should end the connection");
+    assert!(matches!(result, Err(PushPortError::Io(_))));
+    assert!(!client.is_connected());
+}
+
+#[tokio::test]
+async fn from_stream_times_out_when_server_does_not_answer() {
+    let (client_end, _server_end) = tokio::io::duplex(64 * 1024);
+    let options = ConnectOptions::new("darwin.example", 61613, "user", "secret")
+        .connect_timeout(Some(Duration::from_millis(100)));
+
+    let result = NationalRailPushPortClient::from_stream(client_end, options).await;
+    assert!(matches!(result, Err(PushPortError::ConnectTimeout(_))));
+}
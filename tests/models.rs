@@ -0,0 +1,382 @@
+use national_rail_push_port_client::models::{
+    AlarmAction, AlarmKind, AssociationCategory, CallingPoint, MessageCategory, MessageInline,
+    MessageSeverity, ToiletStatus, ToiletType, TrainOrderAction, TrainOrderService,
+};
+use national_rail_push_port_client::Pport;
+
+const DIVERTED_SCHEDULE: &str = r#"<?xml version="1.0" encoding="utf-8"?><Pport xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" ts="2024-03-12T09:14:23.8532587+00:00" version="16.0" xmlns="http://www.thalesgroup.com/rtti/PushPort/v16"><uR updateOrigin="CIS" requestSource="at20" requestID="AM01760220240312091423"><schedule rid="202403127123456" uid="L12345" trainId="1P23" rsid="GW123400" ssd="2024-03-12" toc="GW" status="1" trainCat="XX" isPassengerSvc="false" isCharter="true" xmlns="http://www.thalesgroup.com/rtti/PushPort/Schedules/v3"><OR tpl="PADTON" act="TB" plat="1" ptd="09:00" wtd="09:00" /><PP tpl="ROYAOJN" wtp="09:01:30" /><IP tpl="RDNGSTN" act="T " plat="9" pta="09:25" ptd="09:27" wta="09:24:30" wtd="09:27" /><OPIP tpl="DIDCTEJ" act="OP" wta="09:40" wtd="09:41" affectedByDiversion="true" /><DT tpl="OXFD" act="TF" planAct="TFRM" can="true" rdelay="5" plat="3" pta="09:55" wta="09:55" /><cancelReason tiploc="OXFD">106</cancelReason><divertedVia>DIDCOTP</divertedVia><diversionReason tiploc="RDNGSTN" near="true">570</diversionReason></schedule></uR></Pport>"#;
+
+const TRAIN_STATUS: &str = r#"<?xml version="1.0" encoding="utf-8"?><Pport xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" ts="2024-03-12T09:31:05.6613312+00:00" version="16.0" xmlns="http://www.thalesgroup.com/rtti/PushPort/v16"><uR updateOrigin="TD"><TS rid="202403127123456" uid="L12345" ssd="2024-03-12" xmlns:ns5="http://www.thalesgroup.com/rtti/PushPort/Forecasts/v3"><ns5:LateReason tiploc="RDNGSTN" near="true">104</ns5:LateReason><ns5:Location tpl="RDNGSTN" wta="09:24:30" wtd="09:27" pta="09:25" ptd="09:27"><ns5:arr at="09:29" src="TD" /><ns5:dep et="09:31" wet="09:30" src="Darwin" delayed="true" /><ns5:plat platsup="true" cisPlatsup="true" conf="true" src="P">9B</ns5:plat><ns5:suppr>true</ns5:suppr><ns5:length>8</ns5:length></ns5:Location><ns5:Location tpl="ROYAOJN" wtp="09:01:30"><ns5:pass at="09:02" atRemoved="true" src="TD" /></ns5:Location></TS></uR></Pport>"#;
+
+const ASSOCIATIONS: &str = r#"<?xml version="1.0" encoding="utf-8"?><Pport xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" ts="2024-03-12T05:02:11.1234567+00:00" version="16.0" xmlns="http://www.thalesgroup.com/rtti/PushPort/v16"><uR updateOrigin="CIS"><association tiploc="ASHFKY" category="VV" xmlns="http://www.thalesgroup.com/rtti/PushPort/Schedules/v3"><main rid="202403128765432" wta="06:10" wtd="06:14" pta="06:10" ptd="06:14" /><assoc rid="202403128765433" wtd="06:16" ptd="06:16" /></association><association tiploc="ASHFKY" category="JJ" isCancelled="true" xmlns="http://www.thalesgroup.com/rtti/PushPort/Schedules/v3"><main rid="202403128765440" wta="18:02" /><assoc rid="202403128765441" wta="17:58" /></association><association tiploc="BRGHTN" category="NP" isDeleted="true" xmlns="http://www.thalesgroup.com/rtti/PushPort/Schedules/v3"><main rid="202403128765450" wta="07:30" /><assoc rid="202403128765451" wtd="07:45" /></association><association tiploc="CREWE" category="LK" xmlns="http://www.thalesgroup.com/rtti/PushPort/Schedules/v3"><main rid="202403128765460" wtp="08:00:30" /><assoc rid="202403128765461" wtd="08:05" /></association><deactivated rid="202403117000001" xmlns="http://www.thalesgroup.com/rtti/PushPort/Schedules/v3" /></uR></Pport>"#;
+
+const TRAIN_ORDERS: &str = r#"<?xml version="1.0" encoding="utf-8"?><Pport xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" ts="2024-03-12T08:45:00.0000000+00:00" version="16.0" xmlns="http://www.thalesgroup.com/rtti/PushPort/v16"><uR updateOrigin="CIS"><trainOrder tiploc="CLPHMJC" crs="CLJ" platform="13" xmlns="http://www.thalesgroup.com/rtti/PushPort/TrainOrder/v1"><set><first><rid wta="08:50" wtd="08:51" pta="08:50" ptd="08:51">202403127111111</rid></first><second><trainID>2C45</trainID></second></set></trainOrder><trainOrder tiploc="CLPHMJC" crs="CLJ" platform="14" xmlns="http://www.thalesgroup.com/rtti/PushPort/TrainOrder/v1"><clear /></trainOrder></uR></Pport>"#;
+
+const STATION_MESSAGES: &str = r#"<?xml version="1.0" encoding="utf-8"?><Pport xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" ts="2024-03-12T07:15:42.0000000+00:00" version="16.0" xmlns="http://www.thalesgroup.com/rtti/PushPort/v16"><uR updateOrigin="CIS"><OW id="98765" cat="Train" sev="2" xmlns="http://www.thalesgroup.com/rtti/PushPort/StationMessages/v1"><Station crs="PAD" /><Station crs="RDG" /><Msg>Disruption between <a href="https://www.nationalrail.co.uk/service-disruptions/reading-20240312/">Reading</a> and Oxford. <p>Check your journey (<a href="https://www.nationalrail.co.uk/journey-planner/">journey planner</a>) before travelling, as trains may be cancelled, delayed or revised at short notice.</p></Msg></OW><OW id="98700" cat="PriorOther" sev="0" suppress="true" xmlns="http://www.thalesgroup.com/rtti/PushPort/StationMessages/v1"><Msg>Withdrawn.</Msg></OW></uR></Pport>"#;
+
+const FORMATIONS: &str = r#"<?xml version="1.0" encoding="utf-8"?><Pport xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" ts="2024-03-12T09:20:00.0000000+00:00" version="16.0" xmlns="http://www.thalesgroup.com/rtti/PushPort/v16"><uR updateOrigin="CIS"><scheduleFormations rid="202403127123456" xmlns="http://www.thalesgroup.com/rtti/PushPort/Formations/v1"><formation fid="202403127123456-001" src="CIS"><coaches><coach coachNumber="A" coachClass="First"><toilet status="InService">Accessible</toilet></coach><coach coachNumber="B" coachClass="Standard"><toilet status="NotInService">Standard</toilet></coach><coach coachNumber="C" coachClass="Standard"><toilet>None</toilet></coach><coach coachNumber="D" /></coaches></formation></scheduleFormations><formationLoading fid="202403127123456-001" rid="202403127123456" tpl="RDNGSTN" wta="09:24:30" wtd="09:27" pta="09:25" ptd="09:27" xmlns="http://www.thalesgroup.com/rtti/PushPort/Formations/v1"><loading coachNumber="A" src="CIS">12</loading><loading coachNumber="B">87</loading><loading coachNumber="C">100</loading></formationLoading></uR></Pport>"#;
+
+const TRAIN_ALERTS: &str = r#"<?xml version="1.0" encoding="utf-8"?><Pport xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" ts="2024-03-12T06:30:00.0000000+00:00" version="16.0" xmlns="http://www.thalesgroup.com/rtti/PushPort/v16"><uR updateOrigin="CIS"><trainAlert xmlns="http://www.thalesgroup.com/rtti/PushPort/TrainAlerts/v1"><AlertID>55501</AlertID><AlertServices><AlertService RID="202403127123456" UID="L12345" SSD="2024-03-12"><Location>RDG</Location><Location>DID</Location></AlertService><AlertService RID="202403127123457" UID="L12346" SSD="2024-03-12" /></AlertServices><SendAlertBySMS>true</SendAlertBySMS><SendAlertByEmail>false</SendAlertByEmail><SendAlertByTwitter>true</SendAlertByTwitter><Source>GW</Source><AlertText>Replacement buses between Reading and Didcot.</AlertText><Audience>Customer</Audience><AlertType>Normal</AlertType></trainAlert><trainAlert xmlns="http://www.thalesgroup.com/rtti/PushPort/TrainAlerts/v1"><AlertID>55502</AlertID><Source>NRCC</Source><AlertText>Staff briefing.</AlertText><Audience>Staff</Audience><AlertType>Forced</AlertType></trainAlert></uR></Pport>"#;
+
+const ALARMS: &str = r#"<?xml version="1.0" encoding="utf-8"?><Pport xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" ts="2024-03-12T03:00:00.0000000+00:00" version="16.0" xmlns="http://www.thalesgroup.com/rtti/PushPort/v16"><uR><alarm xmlns="http://www.thalesgroup.com/rtti/PushPort/Alarms/v1"><set id="4521"><tdAreaFail>WY</tdAreaFail></set></alarm><alarm xmlns="http://www.thalesgroup.com/rtti/PushPort/Alarms/v1"><set id="4522"><tdFeedFail /></set></alarm><alarm xmlns="http://www.thalesgroup.com/rtti/PushPort/Alarms/v1"><clear>4521</clear></alarm></uR></Pport>"#;
+
+fn parse(xml: &str) -> Pport {
+    Pport::from_xml(xml).expect("parse Pport")
+}
+
+#[test]
+fn schedule_keeps_calling_points_in_journey_order() {
+    let pport = parse(DIVERTED_SCHEDULE);
+    let schedule = &pport.response().unwrap().schedules[0];
+
+    assert_eq!(schedule.rid, "202403127123456");
+    assert_eq!(schedule.train_id, "1P23");
+    let locations: Vec<&str> = schedule
+        .calling_points
+        .iter()
+        .map(|point| point.location().tpl.as_str())
+        .collect();
+    assert_eq!(
+        locations,
+        ["PADTON", "ROYAOJN", "RDNGSTN", "DIDCTEJ", "OXFD"]
+    );
+    assert!(matches!(
+        schedule.calling_points[0],
+        CallingPoint::Origin(_)
+    ));
+    assert!(matches!(
+        schedule.calling_points[1],
+        CallingPoint::Passing(_)
+    ));
+    assert!(matches!(
+        schedule.calling_points[3],
+        CallingPoint::OperationalIntermediate(_)
+    ));
+    assert!(matches!(
+        schedule.calling_points[4],
+        CallingPoint::Destination(_)
+    ));
+    let reading = schedule.calling_points[2].location();
+    assert_eq!(reading.activities(), ["T"]);
+    assert_eq!(reading.pta.as_deref(), Some("09:25"));
+    assert_eq!(reading.plan_act, None);
+    assert!(!reading.can);
+    assert_eq!(reading.rdelay, 0);
+    let oxford = schedule.calling_points[4].location();
+    assert_eq!(oxford.plan_act.as_deref(), Some("TFRM"));
+    assert!(oxford.can);
+    assert_eq!(oxford.rdelay, 5);
+}
+
+#[test]
+fn schedule_reads_flags_and_their_defaults() {
+    let pport = parse(DIVERTED_SCHEDULE);
+    let schedule = &pport.response().unwrap().schedules[0];
+
+    assert_eq!(schedule.status.as_deref(), Some("1"));
+    assert_eq!(schedule.train_cat.as_deref(), Some("XX"));
+    assert!(!schedule.is_passenger_svc);
+    assert!(schedule.is_charter);
+    assert!(schedule.is_active);
+    assert!(!schedule.deleted);
+    assert!(!schedule.qtrain);
+
+    let xml = DIVERTED_SCHEDULE
+        .replace(
+            r#" status="1" trainCat="XX" isPassengerSvc="false" isCharter="true""#,
+            "",
+        )
+        .replace(
+            r#"toc="GW""#,
+            r#"toc="GW" isActive="false" deleted="true" qtrain="true""#,
+        );
+    let pport = parse(&xml);
+    let schedule = &pport.response().unwrap().schedules[0];
+
+    assert_eq!(schedule.status, None);
+    assert_eq!(schedule.train_cat, None);
+    assert!(schedule.is_passenger_svc);
+    assert!(!schedule.is_charter);
+    assert!(!schedule.is_active);
+    assert!(schedule.deleted);
+    assert!(schedule.qtrain);
+}
+
+#[test]
+fn schedule_reads_diversions() {
+    let pport = parse(DIVERTED_SCHEDULE);
+    let schedule = &pport.response().unwrap().schedules[0];
+
+    assert_eq!(schedule.diverted_via.as_deref(), Some("DIDCOTP"));
+    let reason = schedule.diversion_reason.as_ref().unwrap();
+    assert_eq!(reason.code, 570);
+    assert_eq!(reason.tiploc.as_deref(), Some("RDNGSTN"));
+    assert!(reason.near);
+    let cancelled = schedule.cancel_reason.as_ref().unwrap();
+    assert_eq!(cancelled.code, 106);
+    assert_eq!(cancelled.tiploc.as_deref(), Some("OXFD"));
+    assert!(!cancelled.near);
+}
+
+#[test]
+fn schedule_skips_unknown_children() {
+    let xml = DIVERTED_SCHEDULE.replace(
+        "<divertedVia>",
+        r#"<futureElement code="1"><nested /></futureElement><divertedVia>"#,
+    );
+    let pport = parse(&xml);
+    let schedule = &pport.response().unwrap().schedules[0];
+
+    assert_eq!(schedule.calling_points.len(), 5);
+    assert_eq!(schedule.diverted_via.as_deref(), Some("DIDCOTP"));
+}
+
+#[test]
+fn train_status_reads_forecasts_and_platforms() {
+    let pport = parse(TRAIN_STATUS);
+    let response = pport.response().unwrap();
+    assert_eq!(response.update_origin.as_deref(), Some("TD"));
+    let status = &response.train_status[0];
+
+    assert_eq!(status.rid, "202403127123456");
+    assert_eq!(status.uid, "L12345");
+    assert_eq!(status.ssd, "2024-03-12");
+    let late = status.late_reason.as_ref().unwrap();
+    assert_eq!((late.code, late.near), (104, true));
+
+    let reading = &status.locations[0];
+    assert_eq!(reading.tpl, "RDNGSTN");
+    assert_eq!(reading.wta.as_deref(), Some("09:24:30"));
+    assert_eq!(reading.ptd.as_deref(), Some("09:27"));
+    let arrival = reading.arr.as_ref().unwrap();
+    assert_eq!(arrival.at.as_deref(), Some("09:29"));
+    assert_eq!(arrival.src.as_deref(), Some("TD"));
+    let departure = reading.dep.as_ref().unwrap();
+    assert_eq!(departure.et.as_deref(), Some("09:31"));
+    assert_eq!(departure.wet.as_deref(), Some("09:30"));
+    assert!(departure.delayed);
+    assert_eq!(reading.pass, None);
+    let platform = reading.plat.as_ref().unwrap();
+    assert_eq!(platform.number, "9B");
+    assert!(platform.platsup && platform.cis_platsup && platform.conf);
+    assert_eq!(reading.suppr, Some(true));
+    assert_eq!(reading.length, Some(8));
+
+    let junction = &status.locations[1];
+    assert_eq!(junction.wtp.as_deref(), Some("09:01:30"));
+    let pass = junction.pass.as_ref().unwrap();
+    assert!(pass.at_removed);
+    assert!(!pass.delayed);
+    assert_eq!(junction.plat, None);
+}
+
+#[test]
+fn associations_read_every_category() {
+    let pport = parse(ASSOCIATIONS);
+    let response = pport.response().unwrap();
+    let categories: Vec<AssociationCategory> = response
+        .associations
+        .iter()
+        .map(|association| association.category)
+        .collect();
+    assert_eq!(
+        categories,
+        [
+            AssociationCategory::Divide,
+            AssociationCategory::Join,
+            AssociationCategory::NextWorking,
+            AssociationCategory::Link,
+        ]
+    );
+
+    let divide = &response.associations[0];
+    assert_eq!(divide.tiploc, "ASHFKY");
+    assert!(!divide.is_cancelled && !divide.is_deleted);
+    assert_eq!(divide.main.rid, "202403128765432");
+    assert_eq!(divide.main.ptd.as_deref(), Some("06:14"));
+    assert_eq!(divide.assoc.rid, "202403128765433");
+    assert_eq!(divide.assoc.wta, None);
+    assert!(response.associations[1].is_cancelled);
+    assert!(response.associations[2].is_deleted);
+    assert_eq!(
+        response.associations[3].main.wtp.as_deref(),
+        Some("08:00:30")
+    );
+
+    assert_eq!(response.deactivated[0].rid, "202403117000001");
+}
+
+#[test]
+fn train_orders_read_set_and_clear() {
+    let pport = parse(TRAIN_ORDERS);
+    let orders = &pport.response().unwrap().train_orders;
+
+    assert_eq!(orders[0].crs, "CLJ");
+    assert_eq!(orders[0].platform, "13");
+    let TrainOrderAction::Set(set) = &orders[0].action else {
+        panic!("expected a set order, got {:?}", orders[0].action);
+    };
+    let TrainOrderService::Rid(first) = &set.first.service else {
+        panic!("expected a RID, got {:?}", set.first.service);
+    };
+    assert_eq!(first.rid, "202403127111111");
+    assert_eq!(first.ptd.as_deref(), Some("08:51"));
+    assert_eq!(
+        set.second.as_ref().map(|item| &item.service),
+        Some(&TrainOrderService::TrainId("2C45".to_string()))
+    );
+    assert_eq!(set.third, None);
+
+    assert_eq!(orders[1].platform, "14");
+    assert_eq!(orders[1].action, TrainOrderAction::Clear);
+}
+
+#[test]
+fn station_messages_read_stations_and_body() {
+    let pport = parse(STATION_MESSAGES);
+    let messages = &pport.response().unwrap().station_messages;
+
+    let message = &messages[0];
+    assert_eq!(message.id, 98765);
+    assert_eq!(message.cat, MessageCategory::Train);
+    assert_eq!(message.sev, MessageSeverity::Major);
+    assert!(!message.suppress);
+    assert_eq!(message.crs_codes(), ["PAD", "RDG"]);
+
+    let inlines = &message.msg.inlines;
+    assert_eq!(inlines.len(), 4);
+    assert_eq!(
+        inlines[0],
+        MessageInline::Text("Disruption between".to_string())
+    );
+    let MessageInline::Link(link) = &inlines[1] else {
+        panic!("expected a link, got {:?}", inlines[1]);
+    };
+    assert_eq!(link.text, "Reading");
+    assert!(link.href.ends_with("/reading-20240312/"));
+    assert!(matches!(inlines[3], MessageInline::Paragraph(_)));
+
+    let withdrawn = &messages[1];
+    assert_eq!(withdrawn.cat, MessageCategory::PriorOther);
+    assert!(withdrawn.suppress);
+    assert!(withdrawn.crs_codes().is_empty());
+}
+
+#[test]
+fn station_message_text_restores_spacing_and_paragraphs() {
+    let pport = parse(STATION_MESSAGES);
+    let messages = &pport.response().unwrap().station_messages;
+
+    assert_eq!(
+        messages[0].msg.text(),
+        "Disruption between Reading and Oxford.\n\
+         Check your journey (journey planner) before travelling, as trains may be cancelled, \
+         delayed or revised at short notice."
+    );
+    assert_eq!(messages[1].msg.text(), "Withdrawn.");
+}
+
+#[test]
+fn schedule_formations_read_coaches_and_toilets() {
+    let pport = parse(FORMATIONS);
+    let formations = &pport.response().unwrap().schedule_formations[0];
+
+    assert_eq!(formations.rid, "202403127123456");
+    let formation = &formations.formations[0];
+    assert_eq!(formation.fid, "202403127123456-001");
+    assert_eq!(formation.src.as_deref(), Some("CIS"));
+    let coaches = &formation.coaches.coaches;
+    assert_eq!(coaches.len(), 4);
+    assert_eq!(coaches[0].coach_class.as_deref(), Some("First"));
+    let toilets: Vec<_> = coaches
+        .iter()
+        .map(|coach| {
+            coach
+                .toilet
+                .as_ref()
+                .map(|toilet| (toilet.kind, toilet.status))
+        })
+        .collect();
+    assert_eq!(
+        toilets,
+        [
+            Some((ToiletType::Accessible, Some(ToiletStatus::InService))),
+            Some((ToiletType::Standard, Some(ToiletStatus::NotInService))),
+            Some((ToiletType::None, None)),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn formation_loading_reads_coach_percentages() {
+    let pport = parse(FORMATIONS);
+    let loading = &pport.response().unwrap().formation_loading[0];
+
+    assert_eq!(loading.fid, "202403127123456-001");
+    assert_eq!(loading.tpl, "RDNGSTN");
+    assert_eq!(loading.ptd.as_deref(), Some("09:27"));
+    let percentages: Vec<(&str, u8)> = loading
+        .loading
+        .iter()
+        .map(|coach| (coach.coach_number.as_str(), coach.percentage))
+        .collect();
+    assert_eq!(percentages, [("A", 12), ("B", 87), ("C", 100)]);
+    assert_eq!(loading.loading[0].src.as_deref(), Some("CIS"));
+    assert_eq!(loading.loading[1].src, None);
+}
+
+#[test]
+fn train_alerts_read_services_and_delivery_flags() {
+    let pport = parse(TRAIN_ALERTS);
+    let alerts = &pport.response().unwrap().train_alerts;
+    assert_eq!(alerts.len(), 2);
+
+    let buses = &alerts[0];
+    assert_eq!(buses.alert_id, "55501");
+    let services = &buses.alert_services.services;
+    assert_eq!(services.len(), 2);
+    assert_eq!(services[0].rid, "202403127123456");
+    assert_eq!(services[0].uid, "L12345");
+    assert_eq!(services[0].ssd, "2024-03-12");
+    assert_eq!(services[0].locations, ["RDG", "DID"]);
+    assert_eq!(services[1].rid, "202403127123457");
+    assert!(services[1].locations.is_empty());
+    assert!(buses.send_alert_by_sms);
+    assert!(!buses.send_alert_by_email);
+    assert!(buses.send_alert_by_twitter);
+    assert_eq!(buses.source, "GW");
+    assert_eq!(
+        buses.alert_text,
+        "Replacement buses between Reading and Didcot."
+    );
+    assert_eq!(buses.audience, "Customer");
+    assert_eq!(buses.alert_type, "Normal");
+
+    // Services and delivery flags are optional.
+    let briefing = &alerts[1];
+    assert_eq!(briefing.alert_id, "55502");
+    assert!(briefing.alert_services.services.is_empty());
+    assert!(!briefing.send_alert_by_sms);
+    assert!(!briefing.send_alert_by_email);
+    assert!(!briefing.send_alert_by_twitter);
+    assert_eq!(briefing.audience, "Staff");
+    assert_eq!(briefing.alert_type, "Forced");
+}
+
+#[test]
+fn alarms_read_set_and_clear() {
+    let pport = parse(ALARMS);
+    let alarms = &pport.response().unwrap().alarms;
+
+    let AlarmAction::Set(area) = &alarms[0].action else {
+        panic!("expected a set alarm, got {:?}", alarms[0].action);
+    };
+    assert_eq!(area.id, 4521);
+    assert_eq!(area.kind, AlarmKind::TdAreaFail("WY".to_string()));
+    let AlarmAction::Set(feed) = &alarms[1].action else {
+        panic!("expected a set alarm, got {:?}", alarms[1].action);
+    };
+    assert!(matches!(feed.kind, AlarmKind::TdFeedFail(_)));
+    assert_eq!(alarms[2].action, AlarmAction::Clear(4521));
+}
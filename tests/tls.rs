@@ -0,0 +1,116 @@
+#![cfg(feature = "tls")]
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+use national_rail_push_port_client::rustls::crypto::ring;
+use national_rail_push_port_client::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use national_rail_push_port_client::rustls::ServerConfig;
+use national_rail_push_port_client::{
+    ConnectOptions, NationalRailPushPortClient, PushPortError, TlsOptions,
+};
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+const SERVER_NAME: &str = "broker.test";
+
+/// A certificate authority and a server certificate it has signed for [`SERVER_NAME`].
+struct TestPki {
+    ca_pem: String,
+    server_chain: Vec<CertificateDer<'static>>,
+    server_key: PrivateKeyDer<'static>,
+}
+
+fn generate_pki(ca_name: &str) -> TestPki {
+    let ca_key = KeyPair::generate().unwrap();
+    let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca_params
+        .distinguished_name
+        .push(DnType::CommonName, ca_name);
+    let ca = ca_params.self_signed(&ca_key).unwrap();
+
+    let server_key = KeyPair::generate().unwrap();
+    let server_params = CertificateParams::new(vec![SERVER_NAME.to_string()]).unwrap();
+    let server = server_params.signed_by(&server_key, &ca, &ca_key).unwrap();
+
+    TestPki {
+        ca_pem: ca.pem(),
+        server_chain: vec![server.der().clone()],
+        server_key: PrivateKeyDer::Pkcs8(server_key.serialize_der().into()),
+    }
+}
+
+/// Accepts one TLS connection and answers its CONNECT frame, returning the listening port.
+async fn serve_stomp_over_tls(pki: &TestPki) -> u16 {
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(pki.server_chain.clone(), pki.server_key.clone_key())
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        // A client that rejects the certificate fails the handshake here.
+        let Ok(mut stream) = acceptor.accept(stream).await else {
+            return;
+        };
+        let mut connect = Vec::new();
+        while !connect.ends_with(&[0]) {
+            connect.push(stream.read_u8().await.unwrap());
+        }
+        stream
+            .write_all(b"CONNECTED\nversion:1.2\nserver:tls-test\n\n\0")
+            .await
+            .unwrap();
+        // Keep the connection open until the client goes away.
+        let _ = stream.read_u8().await;
+    });
+    port
+}
+
+fn options(port: u16, tls: TlsOptions) -> ConnectOptions {
+    ConnectOptions::new("127.0.0.1", port, "user", "password")
+        .heart_beat(Duration::ZERO, Duration::ZERO)
+        .connect_timeout(Some(Duration::from_secs(5)))
+        .tls(tls)
+}
+
+#[tokio::test]
+async fn connects_with_private_root_and_server_name() {
+    let pki = generate_pki("Test CA");
+    let port = serve_stomp_over_tls(&pki).await;
+
+    let tls = TlsOptions::new()
+        .root_certificates_pem(pki.ca_pem.as_bytes())
+        .unwrap()
+        .server_name(SERVER_NAME);
+    let client = NationalRailPushPortClient::connect_with(options(port, tls))
+        .await
+        .unwrap();
+    assert_eq!(client.connection_info().server.as_deref(), Some("tls-test"));
+}
+
+#[tokio::test]
+async fn untrusted_certificate_is_reported_as_tls_error() {
+    let pki = generate_pki("Test CA");
+    let port = serve_stomp_over_tls(&pki).await;
+
+    let other = generate_pki("Other CA");
+    let tls = TlsOptions::new()
+        .root_certificates_pem(other.ca_pem.as_bytes())
+        .unwrap()
+        .server_name(SERVER_NAME);
+    let result = NationalRailPushPortClient::connect_with(options(port, tls)).await;
+    assert!(
+        matches!(result, Err(PushPortError::Tls(_))),
+        "{:?}",
+        result.err()
+    );
+}